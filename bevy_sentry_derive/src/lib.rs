@@ -0,0 +1,173 @@
+//! Derive macros for `bevy_sentry`
+
+#![forbid(unsafe_code)]
+#![warn(unused_imports, missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta};
+
+const SENTRY_ATTRIBUTE: &str = "sentry";
+const KEY_ATTRIBUTE: &str = "key";
+const RENAME_ATTRIBUTE: &str = "rename";
+const SKIP_ATTRIBUTE: &str = "skip";
+const REDACT_ATTRIBUTE: &str = "redact";
+
+/// Value Sentry uses for data that was scrubbed
+const REDACTED_VALUE: &str = "[Filtered]";
+
+/// Derive `SentryContext` for a resource with named fields
+///
+/// Every field is serialized into the context map under its own name. Supported attributes:
+/// - `#[sentry(key = "...")]` on the struct sets the context key (defaults to the struct name)
+/// - `#[sentry(rename = "...")]` on a field changes the name it is reported under
+/// - `#[sentry(skip)]` on a field leaves it out of the context
+/// - `#[sentry(redact)]` on a field reports it with a redacted value
+///
+/// Fields that fail to serialize are left out of the context and the error is logged.
+#[proc_macro_derive(SentryContext, attributes(sentry))]
+pub fn derive_sentry_context(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    impl_sentry_context(ast)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn impl_sentry_context(ast: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &ast.ident;
+    let key = match parse_container_attributes(&ast.attrs)? {
+        Some(key) => key,
+        None => name.to_string(),
+    };
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &ast,
+                    "SentryContext can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &ast,
+                "SentryContext can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut inserts = vec![];
+    for field in fields {
+        let attributes = parse_field_attributes(&field.attrs)?;
+        if attributes.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = attributes.rename.unwrap_or_else(|| ident.to_string());
+        inserts.push(if attributes.redact {
+            quote!(context.insert(
+                #field_name.to_owned(),
+                ::bevy_sentry::protocol::Value::from(#REDACTED_VALUE),
+            );)
+        } else {
+            quote!(if let Some(value) =
+                ::bevy_sentry::serialize_context_field(#key, #field_name, &self.#ident)
+            {
+                context.insert(#field_name.to_owned(), value);
+            })
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_sentry::SentryContext for #name #ty_generics #where_clause {
+            fn key() -> &'static str {
+                #key
+            }
+
+            fn context(
+                &self,
+            ) -> ::std::collections::BTreeMap<::std::string::String, ::bevy_sentry::protocol::Value>
+            {
+                let mut context = ::std::collections::BTreeMap::new();
+                #(#inserts)*
+
+                context
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    rename: Option<String>,
+    skip: bool,
+    redact: bool,
+}
+
+fn parse_container_attributes(attributes: &[Attribute]) -> Result<Option<String>, Error> {
+    let mut key = None;
+    for meta in sentry_attributes(attributes)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if name_value.path.is_ident(KEY_ATTRIBUTE) =>
+            {
+                key = Some(string_literal(&name_value.lit)?);
+            }
+            other => return Err(Error::new_spanned(other, "Unknown sentry attribute")),
+        }
+    }
+
+    Ok(key)
+}
+
+fn parse_field_attributes(attributes: &[Attribute]) -> Result<FieldAttributes, Error> {
+    let mut field_attributes = FieldAttributes::default();
+    for meta in sentry_attributes(attributes)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if name_value.path.is_ident(RENAME_ATTRIBUTE) =>
+            {
+                field_attributes.rename = Some(string_literal(&name_value.lit)?);
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident(SKIP_ATTRIBUTE) => {
+                field_attributes.skip = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident(REDACT_ATTRIBUTE) => {
+                field_attributes.redact = true;
+            }
+            other => return Err(Error::new_spanned(other, "Unknown sentry attribute")),
+        }
+    }
+
+    Ok(field_attributes)
+}
+
+fn sentry_attributes(attributes: &[Attribute]) -> Result<Vec<NestedMeta>, Error> {
+    let mut nested = vec![];
+    for attribute in attributes
+        .iter()
+        .filter(|attribute| attribute.path.is_ident(SENTRY_ATTRIBUTE))
+    {
+        match attribute.parse_meta()? {
+            Meta::List(list) => nested.extend(list.nested),
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    "Expected sentry attribute in the form #[sentry(...)]",
+                ))
+            }
+        }
+    }
+
+    Ok(nested)
+}
+
+fn string_literal(literal: &Lit) -> Result<String, Error> {
+    match literal {
+        Lit::Str(string) => Ok(string.value()),
+        other => Err(Error::new_spanned(other, "Expected a string literal")),
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{short_type_name, Sentry};
+use bevy::ecs::system::Resource;
+use bevy::prelude::{EventReader, Res};
+use sentry::protocol::Breadcrumb;
+use std::fmt::Debug;
+
+/// Converts events of type `E` to Sentry breadcrumbs
+pub(crate) struct BreadcrumbMapper<E>(Box<dyn Fn(&E) -> Breadcrumb + Send + Sync>);
+
+impl<E> BreadcrumbMapper<E> {
+    pub(crate) fn new(mapper: impl Fn(&E) -> Breadcrumb + Send + Sync + 'static) -> Self {
+        BreadcrumbMapper(Box::new(mapper))
+    }
+}
+
+/// Breadcrumb with the event's `Debug` representation as message and its type as category
+pub(crate) fn debug_breadcrumb<E: Debug>(event: &E) -> Breadcrumb {
+    Breadcrumb {
+        category: Some(short_type_name::<E>().to_owned()),
+        message: Some(format!("{:?}", event)),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn record_event_breadcrumbs<E: Resource>(
+    sentry: Option<Res<Sentry>>,
+    mapper: Res<BreadcrumbMapper<E>>,
+    mut events: EventReader<E>,
+) {
+    for event in events.iter() {
+        if let Some(sentry) = &sentry {
+            sentry.hub().add_breadcrumb((mapper.0)(event));
+        }
+    }
+}
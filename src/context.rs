@@ -0,0 +1,225 @@
+use crate::Sentry;
+use bevy::ecs::system::Resource;
+use bevy::ecs::world::World;
+use bevy::log::error;
+use bevy::prelude::{Local, Res};
+use sentry::protocol::{value::to_value, Context, Value};
+use sentry::Hub;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+/// A resource that is reported to Sentry as custom context
+///
+/// The value mapping will be passed to Sentry as a custom context with every event.
+/// See https://docs.sentry.io/product/sentry-basics/enrich-data/#types-of-data
+///
+/// This trait can be derived for structs with named fields. See the
+/// [derive macro](derive@crate::SentryContext) for the supported attributes. Fields that fail to
+/// serialize are left out of the context with an error in the log.
+///
+/// The derive macro rejects tuple structs, enums and unknown attributes:
+/// ```compile_fail
+/// # use bevy_sentry::SentryContext;
+/// #[derive(SentryContext)]
+/// struct Score(u32);
+/// ```
+/// ```compile_fail
+/// # use bevy_sentry::SentryContext;
+/// #[derive(SentryContext)]
+/// enum Difficulty {
+///     Easy,
+///     Hard,
+/// }
+/// ```
+/// ```compile_fail
+/// # use bevy_sentry::SentryContext;
+/// #[derive(SentryContext)]
+/// struct Player {
+///     #[sentry(hide)]
+///     name: String,
+/// }
+/// ```
+pub trait SentryContext: Resource {
+    /// Key under which the context is attached to Sentry events
+    fn key() -> &'static str;
+
+    /// Build the context from the current state of the resource
+    fn context(&self) -> BTreeMap<String, Value>;
+}
+
+/// Serialize a field of a derived [`SentryContext`]
+///
+/// Used by the derive macro. Errors are logged like for
+/// [serialized contexts](crate::SentryApp::register_serialized_context).
+#[doc(hidden)]
+pub fn serialize_context_field<T: Serialize>(key: &str, field: &str, value: &T) -> Option<Value> {
+    match to_value(value) {
+        Ok(value) => Some(value),
+        Err(serialization_error) => {
+            error!(
+                "Failed to serialize the field '{}' of the Sentry context '{}': {}",
+                field, key, serialization_error
+            );
+            None
+        }
+    }
+}
+
+/// Marks the resource `T` as registered Sentry context
+///
+/// Bevy does not support removing systems, so unregistering a context deactivates it instead.
+pub(crate) struct ContextRegistration<T> {
+    pub(crate) key: &'static str,
+    pub(crate) active: bool,
+    _phantom_data: PhantomData<T>,
+}
+
+impl<T> ContextRegistration<T> {
+    pub(crate) fn new(key: &'static str) -> Self {
+        ContextRegistration {
+            key,
+            active: true,
+            _phantom_data: PhantomData::default(),
+        }
+    }
+}
+
+/// Attaches the registered contexts once Sentry is initialized
+///
+/// Contexts can be registered before or after the [`SentryPlugin`](crate::SentryPlugin) is
+/// added, so their current values are attached as soon as both happened.
+#[derive(Default)]
+pub(crate) struct RegisteredContexts(Vec<fn(&World, &Hub)>);
+
+impl RegisteredContexts {
+    pub(crate) fn add(&mut self, apply: fn(&World, &Hub)) {
+        self.0.push(apply);
+    }
+
+    pub(crate) fn apply(&self, world: &World, hub: &Hub) {
+        for apply in &self.0 {
+            apply(world, hub);
+        }
+    }
+}
+
+/// Attach the context `T` if it is registered and the resource exists
+pub(crate) fn apply_registered_context<T: SentryContext>(world: &World, hub: &Hub) {
+    if let (Some(registration), Some(context)) = (
+        world.get_resource::<ContextRegistration<T>>(),
+        world.get_resource::<T>(),
+    ) {
+        if registration.active {
+            apply_sentry_context(hub, context);
+        }
+    }
+}
+
+/// Attach the serialized resource `T` if it is registered and exists
+pub(crate) fn apply_registered_serialized_context<T: Resource + Serialize>(
+    world: &World,
+    hub: &Hub,
+) {
+    if let (Some(registration), Some(resource)) = (
+        world.get_resource::<ContextRegistration<T>>(),
+        world.get_resource::<T>(),
+    ) {
+        if registration.active {
+            apply_serialized_context(hub, registration.key, resource);
+        }
+    }
+}
+
+fn apply_sentry_context<T: SentryContext>(hub: &Hub, context: &T) {
+    hub.configure_scope(|scope| {
+        scope.set_context(T::key(), Context::Other(context.context()));
+    });
+}
+
+fn apply_serialized_context<T: Serialize>(hub: &Hub, key: &str, resource: &T) {
+    let context = match to_value(resource) {
+        Ok(Value::Object(map)) => map.into_iter().collect(),
+        Ok(value) => {
+            let mut context = BTreeMap::new();
+            context.insert("value".to_owned(), value);
+
+            context
+        }
+        Err(serialization_error) => {
+            error!(
+                "Failed to serialize the Sentry context '{}': {}",
+                key, serialization_error
+            );
+            return;
+        }
+    };
+    hub.configure_scope(|scope| {
+        scope.set_context(key, Context::Other(context));
+    });
+}
+
+pub(crate) fn remove_context(hub: &Hub, key: &str) {
+    hub.configure_scope(|scope| {
+        scope.remove_context(key);
+    });
+}
+
+pub(crate) fn set_sentry_context<T: SentryContext>(
+    sentry: Option<Res<Sentry>>,
+    registration: Res<ContextRegistration<T>>,
+    context: Option<Res<T>>,
+    mut attached: Local<bool>,
+) {
+    if let Some(sentry) = sentry {
+        sync_context(
+            sentry.hub(),
+            &registration,
+            context,
+            &mut attached,
+            |hub, _, context| apply_sentry_context(hub, context),
+        );
+    }
+}
+
+pub(crate) fn set_serialized_context<T: Resource + Serialize>(
+    sentry: Option<Res<Sentry>>,
+    registration: Res<ContextRegistration<T>>,
+    resource: Option<Res<T>>,
+    mut attached: Local<bool>,
+) {
+    if let Some(sentry) = sentry {
+        sync_context(
+            sentry.hub(),
+            &registration,
+            resource,
+            &mut attached,
+            apply_serialized_context,
+        );
+    }
+}
+
+/// Attach the context on changes and remove it again when the resource is gone or the
+/// context got unregistered
+fn sync_context<T: Resource>(
+    hub: &Hub,
+    registration: &ContextRegistration<T>,
+    resource: Option<Res<T>>,
+    attached: &mut bool,
+    apply: impl FnOnce(&Hub, &str, &T),
+) {
+    match resource {
+        Some(resource) if registration.active => {
+            if resource.is_changed() || !*attached {
+                apply(hub, registration.key, &*resource);
+                *attached = true;
+            }
+        }
+        _ => {
+            if *attached {
+                remove_context(hub, registration.key);
+                *attached = false;
+            }
+        }
+    }
+}
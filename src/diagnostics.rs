@@ -0,0 +1,177 @@
+use crate::{add_snapshot_system, Sentry};
+use bevy::app::App;
+use bevy::core::Time;
+use bevy::diagnostic::{DiagnosticId, Diagnostics};
+use bevy::prelude::{Local, Res};
+use sentry::protocol::{Context, Event, Map, Value};
+use sentry::{Hub, TransactionContext};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Key of the context holding the latest diagnostic values
+pub(crate) const DIAGNOSTICS_CONTEXT: &str = "bevy.diagnostics";
+
+const AGGREGATES_TRANSACTION: &str = "diagnostics";
+const AGGREGATES_OPERATION: &str = "bevy.diagnostics";
+
+/// Configuration for reporting Bevy's `Diagnostics`
+#[derive(Clone)]
+pub(crate) struct DiagnosticsSettings {
+    pub(crate) enabled: bool,
+    pub(crate) allowlist: Option<Vec<DiagnosticId>>,
+    pub(crate) aggregation_interval: Option<Duration>,
+}
+
+impl Default for DiagnosticsSettings {
+    fn default() -> Self {
+        DiagnosticsSettings {
+            enabled: true,
+            allowlist: None,
+            aggregation_interval: None,
+        }
+    }
+}
+
+struct DiagnosticsReporting {
+    settings: DiagnosticsSettings,
+    latest: Arc<Mutex<Map<String, Value>>>,
+}
+
+impl DiagnosticsReporting {
+    fn is_reported(&self, id: DiagnosticId) -> bool {
+        match &self.settings.allowlist {
+            Some(allowlist) => allowlist.contains(&id),
+            None => true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Aggregates {
+    since: f64,
+    values: BTreeMap<String, Aggregate>,
+}
+
+struct Aggregate {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl Aggregate {
+    fn new(value: f64) -> Self {
+        Aggregate {
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn to_value(&self) -> Value {
+        let mut value = Map::new();
+        value.insert("min".to_owned(), self.min.into());
+        value.insert("max".to_owned(), self.max.into());
+        value.insert("avg".to_owned(), (self.sum / self.count as f64).into());
+        value.insert("count".to_owned(), self.count.into());
+
+        Value::Object(value.into_iter().collect())
+    }
+}
+
+pub(crate) fn setup_diagnostics(app: &mut App, hub: &Hub, settings: DiagnosticsSettings) {
+    if !settings.enabled {
+        return;
+    }
+
+    let latest = Arc::new(Mutex::new(Map::new()));
+    let processor_latest = latest.clone();
+    hub.configure_scope(|scope| {
+        scope.add_event_processor(move |event| Some(attach_diagnostics(event, &processor_latest)));
+    });
+    app.insert_resource(DiagnosticsReporting { settings, latest });
+    add_snapshot_system(app, report_diagnostics);
+}
+
+fn report_diagnostics(
+    sentry: Res<Sentry>,
+    diagnostics: Option<Res<Diagnostics>>,
+    reporting: Res<DiagnosticsReporting>,
+    time: Option<Res<Time>>,
+    mut aggregates: Local<Aggregates>,
+) {
+    let diagnostics = match diagnostics {
+        Some(diagnostics) => diagnostics,
+        None => return,
+    };
+    let aggregate = reporting.settings.aggregation_interval.is_some();
+
+    let mut latest = Map::new();
+    for diagnostic in diagnostics
+        .iter()
+        .filter(|diagnostic| reporting.is_reported(diagnostic.id))
+    {
+        if let Some(average) = diagnostic.average() {
+            latest.insert(diagnostic.name.to_string(), average.into());
+        }
+        if let (true, Some(value)) = (aggregate, diagnostic.value()) {
+            aggregates
+                .values
+                .entry(diagnostic.name.to_string())
+                .and_modify(|aggregate| aggregate.add(value))
+                .or_insert_with(|| Aggregate::new(value));
+        }
+    }
+    if let Ok(mut reported) = reporting.latest.lock() {
+        *reported = latest;
+    }
+
+    // Aggregation needs the `Time` of Bevy's `CorePlugin`
+    if let (Some(interval), Some(time)) = (reporting.settings.aggregation_interval, time) {
+        let now = time.seconds_since_startup();
+        if now - aggregates.since >= interval.as_secs_f64() {
+            if !aggregates.values.is_empty() {
+                send_aggregates(sentry.hub(), &aggregates.values);
+            }
+            aggregates.values.clear();
+            aggregates.since = now;
+        }
+    }
+}
+
+/// Send the aggregated diagnostics of the last interval as data of a Sentry transaction
+///
+/// The transaction is sampled with the configured traces sample rate.
+fn send_aggregates(hub: &Hub, values: &BTreeMap<String, Aggregate>) {
+    let context = TransactionContext::new(AGGREGATES_TRANSACTION, AGGREGATES_OPERATION);
+    let transaction = hub.start_transaction(context);
+    for (name, aggregate) in values {
+        transaction.set_data(name, aggregate.to_value());
+    }
+    transaction.finish();
+}
+
+fn attach_diagnostics(
+    mut event: Event<'static>,
+    latest: &Mutex<Map<String, Value>>,
+) -> Event<'static> {
+    if let Ok(latest) = latest.lock() {
+        if !latest.is_empty() {
+            event.contexts.insert(
+                DIAGNOSTICS_CONTEXT.to_owned(),
+                Context::Other(latest.clone()),
+            );
+        }
+    }
+
+    event
+}
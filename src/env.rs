@@ -0,0 +1,169 @@
+use crate::SentryConfig;
+use bevy::log::warn;
+use sentry::IntoDsn;
+use std::borrow::Cow;
+use std::env;
+
+const DSN: &str = "dsn";
+const ENVIRONMENT: &str = "environment";
+const RELEASE: &str = "release";
+const SAMPLE_RATE: &str = "sample-rate";
+const TRACES_SAMPLE_RATE: &str = "traces-sample-rate";
+const DISABLED: &str = "disabled";
+
+/// Settings that can be overridden, e.g. by `SENTRY_TRACES_SAMPLE_RATE` or
+/// `--sentry-traces-sample-rate`
+const OVERRIDES: [&str; 6] = [
+    DSN,
+    ENVIRONMENT,
+    RELEASE,
+    SAMPLE_RATE,
+    TRACES_SAMPLE_RATE,
+    DISABLED,
+];
+
+const ARG_PREFIX: &str = "--sentry-";
+
+/// Overrides from `SENTRY_*` environment variables
+pub(crate) fn env_overrides() -> Vec<(&'static str, String)> {
+    OVERRIDES
+        .iter()
+        .filter_map(|setting| {
+            let variable = format!("SENTRY_{}", setting.to_uppercase().replace('-', "_"));
+            env::var(variable).ok().map(|value| (*setting, value))
+        })
+        .collect()
+}
+
+/// Overrides from `--sentry-<setting> <value>` or `--sentry-<setting>=<value>` arguments
+///
+/// `--sentry-disabled` does not need a value.
+pub(crate) fn arg_overrides(args: impl IntoIterator<Item = String>) -> Vec<(&'static str, String)> {
+    let mut overrides = vec![];
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let arg = match arg.strip_prefix(ARG_PREFIX) {
+            Some(arg) => arg.to_owned(),
+            None => continue,
+        };
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+            None => (arg, None),
+        };
+        let setting = match OVERRIDES.iter().find(|setting| **setting == name) {
+            Some(setting) => *setting,
+            None => {
+                warn!("Unknown Sentry argument '{}{}'", ARG_PREFIX, name);
+                continue;
+            }
+        };
+        let value = match value {
+            Some(value) => value,
+            None if setting == DISABLED => "true".to_owned(),
+            None => match args.next_if(|value| !value.starts_with("--")) {
+                Some(value) => value,
+                None => {
+                    warn!("Missing value for Sentry argument '{}{}'", ARG_PREFIX, name);
+                    continue;
+                }
+            },
+        };
+        overrides.push((setting, value));
+    }
+
+    overrides
+}
+
+pub(crate) fn apply_override(config: &mut SentryConfig, setting: &str, value: &str) {
+    let options = &mut config.options;
+    match setting {
+        DSN => match value.into_dsn() {
+            Ok(dsn) => options.dsn = dsn,
+            Err(error) => warn!("Invalid Sentry DSN '{}': {}", value, error),
+        },
+        ENVIRONMENT => options.environment = Some(Cow::Owned(value.to_owned())),
+        RELEASE => options.release = Some(Cow::Owned(value.to_owned())),
+        SAMPLE_RATE => {
+            if let Some(sample_rate) = parse_sample_rate(setting, value) {
+                options.sample_rate = sample_rate;
+            }
+        }
+        TRACES_SAMPLE_RATE => {
+            if let Some(sample_rate) = parse_sample_rate(setting, value) {
+                options.traces_sample_rate = sample_rate;
+            }
+        }
+        DISABLED => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" => config.enabled = false,
+            "0" | "false" | "no" => config.enabled = true,
+            _ => warn!("Invalid value '{}' for Sentry setting '{}'", value, setting),
+        },
+        _ => {}
+    }
+}
+
+fn parse_sample_rate(setting: &str, value: &str) -> Option<f32> {
+    match value.parse::<f32>() {
+        Ok(sample_rate) if (0. ..=1.).contains(&sample_rate) => Some(sample_rate),
+        _ => {
+            warn!(
+                "Invalid sample rate '{}' for Sentry setting '{}', expected a value between 0 and 1",
+                value, setting
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(args: &[&str]) -> Vec<(&'static str, String)> {
+        arg_overrides(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_values_after_equals_sign() {
+        assert_eq!(
+            overrides(&["--sentry-environment=qa", "--sentry-sample-rate=0.5"]),
+            vec![
+                (ENVIRONMENT, "qa".to_owned()),
+                (SAMPLE_RATE, "0.5".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_values_in_next_argument() {
+        assert_eq!(
+            overrides(&["--sentry-release", "1.2.3", "--fullscreen"]),
+            vec![(RELEASE, "1.2.3".to_owned())]
+        );
+    }
+
+    #[test]
+    fn disabled_does_not_need_a_value() {
+        assert_eq!(
+            overrides(&["--sentry-disabled", "level-1"]),
+            vec![(DISABLED, "true".to_owned())]
+        );
+        assert_eq!(
+            overrides(&["--sentry-disabled=false"]),
+            vec![(DISABLED, "false".to_owned())]
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_and_incomplete_arguments() {
+        assert_eq!(
+            overrides(&[
+                "--sentry-unknown=1",
+                "--fullscreen",
+                "--sentry-dsn",
+                "--sentry-environment",
+            ]),
+            vec![]
+        );
+    }
+}
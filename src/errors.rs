@@ -0,0 +1,54 @@
+use crate::Sentry;
+use bevy::ecs::system::{BoxedSystem, In, IntoChainSystem, IntoSystem, Res};
+use sentry::Hub;
+use std::any::type_name;
+use std::error::Error;
+
+/// Tag holding the name of the system an event originates from
+pub(crate) const SYSTEM_TAG: &str = "bevy.system";
+
+/// Capture errors returned from a system in Sentry
+///
+/// Use it to handle the result of a system by chaining: `my_system.chain(capture_errors)`.
+/// Errors are captured including their source chain. The events are not tagged with the failing
+/// system, because a chained handler does not know which system it is chained to. Use
+/// [`report_errors`](ReportErrors::report_errors) instead to tag them with the system's name.
+pub fn capture_errors<T, E>(In(result): In<Result<T, E>>, sentry: Option<Res<Sentry>>)
+where
+    E: Error + 'static,
+{
+    if let (Err(error), Some(sentry)) = (result, sentry) {
+        sentry.hub().capture_error(&error);
+    }
+}
+
+/// Extension trait to report errors returned from systems to Sentry
+pub trait ReportErrors<Params, T, E>: IntoSystem<(), Result<T, E>, Params> {
+    /// Capture errors returned from this system in Sentry
+    ///
+    /// Errors are captured including their source chain and tagged with the system's type name
+    /// as `bevy.system`.
+    fn report_errors(self) -> BoxedSystem<(), ()>;
+}
+
+impl<S, Params, T, E> ReportErrors<Params, T, E> for S
+where
+    S: IntoSystem<(), Result<T, E>, Params>,
+    T: Send + Sync + 'static,
+    E: Error + Send + Sync + 'static,
+{
+    fn report_errors(self) -> BoxedSystem<(), ()> {
+        let system_name = type_name::<S>();
+        Box::new(self.chain(
+            move |In(result): In<Result<T, E>>, sentry: Option<Res<Sentry>>| {
+                if let (Err(error), Some(sentry)) = (result, sentry) {
+                    // Systems run in parallel, so the tag is set on a hub of its own instead of
+                    // a scope pushed on the shared hub
+                    let hub = Hub::new_from_top(sentry.hub());
+                    hub.configure_scope(|scope| scope.set_tag(SYSTEM_TAG, system_name));
+                    hub.capture_error(&error);
+                }
+            },
+        ))
+    }
+}
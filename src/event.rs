@@ -0,0 +1,61 @@
+use crate::Sentry;
+use bevy::prelude::{EventReader, Res};
+use sentry::protocol::Event;
+use sentry::Level;
+use std::error::Error;
+
+/// Bevy event to capture messages and errors in Sentry from any system
+///
+/// Send it with an `EventWriter<SentryEvent>`. The events are captured at the end of the frame,
+/// after contexts and snapshots of the world have been updated.
+pub enum SentryEvent {
+    /// Capture a message with the given level
+    Message {
+        /// The message
+        message: String,
+        /// Level of the message
+        level: Level,
+    },
+    /// Capture an error including its source chain
+    Error(Box<dyn Error + Send + Sync>),
+    /// Capture a custom event
+    Event(Event<'static>),
+}
+
+impl SentryEvent {
+    /// Create a message event
+    pub fn message(message: impl Into<String>, level: Level) -> Self {
+        SentryEvent::Message {
+            message: message.into(),
+            level,
+        }
+    }
+
+    /// Create an error event
+    pub fn error(error: impl Error + Send + Sync + 'static) -> Self {
+        SentryEvent::Error(Box::new(error))
+    }
+}
+
+pub(crate) fn capture_sentry_events(
+    sentry: Option<Res<Sentry>>,
+    mut events: EventReader<SentryEvent>,
+) {
+    let hub = match &sentry {
+        Some(sentry) => sentry.hub(),
+        None => return,
+    };
+    for event in events.iter() {
+        match event {
+            SentryEvent::Message { message, level } => {
+                hub.capture_message(message, *level);
+            }
+            SentryEvent::Error(error) => {
+                hub.capture_error(error.as_ref());
+            }
+            SentryEvent::Event(event) => {
+                hub.capture_event(event.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{Sentry, SentrySystem};
+use bevy::app::{App, AppExit, CoreStage};
+use bevy::ecs::event::{Events, ManualEventReader};
+use bevy::ecs::schedule::ParallelSystemDescriptorCoercion;
+use bevy::prelude::{EventReader, Local, Res};
+use bevy::window::WindowCloseRequested;
+use sentry::protocol::SessionStatus;
+
+pub(crate) fn setup_exit_flush(app: &mut App) {
+    app.add_system_to_stage(CoreStage::Last, flush_on_exit.after(SentrySystem::Capture));
+}
+
+/// Send pending events before the app quits
+///
+/// The app runner may exit the process without dropping the [`Sentry`] resource, so the session
+/// is ended and the client is closed as soon as the app exits. The client bound to the hub may
+/// differ from the initial one if settings were reloaded. A close request of a window only
+/// flushes the pending events, because the app may keep running afterwards.
+fn flush_on_exit(
+    sentry: Res<Sentry>,
+    mut app_exit: EventReader<AppExit>,
+    close_requests: Option<Res<Events<WindowCloseRequested>>>,
+    mut close_requests_reader: Local<ManualEventReader<WindowCloseRequested>>,
+) {
+    if app_exit.iter().next().is_some() {
+        sentry.hub().end_session_with_status(SessionStatus::Exited);
+        if let Some(client) = sentry.hub().client() {
+            client.close(None);
+        }
+        if let Some(guard) = &sentry.guard {
+            guard.close(None);
+        }
+    } else if let Some(close_requests) = close_requests {
+        if close_requests_reader.iter(&close_requests).next().is_some() {
+            if let Some(client) = sentry.hub().client() {
+                client.flush(None);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::Sentry;
+use bevy::app::{App, Plugin};
+use bevy::core::Time;
+use bevy::prelude::{Local, Res};
+use sentry::protocol::{Breadcrumb, Context, Map};
+use sentry::{Hub, Level};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Reports frame hitches as warning events to Sentry
+///
+/// A frame is considered a hitch if it takes longer than the configured budget or, optionally,
+/// a multiple of a percentile of the recent frame times. The recent frame times are attached to
+/// the event as breadcrumbs.
+///
+/// Configure the detection by inserting [`HitchSettings`] before adding this plugin.
+#[derive(Default)]
+pub struct SentryHitchPlugin;
+
+impl Plugin for SentryHitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitchSettings>()
+            .add_system(detect_hitches);
+    }
+}
+
+/// Settings for the [`SentryHitchPlugin`]
+pub struct HitchSettings {
+    /// Frames taking longer than this are reported
+    pub frame_budget: Duration,
+    /// Additionally report frames that are slow compared to the recent frame times
+    pub percentile_threshold: Option<PercentileThreshold>,
+    /// Number of recent frame times kept for breadcrumbs and the percentile threshold
+    pub history_length: usize,
+    /// Minimum time between two reports
+    pub cooldown: Duration,
+    /// Maximum number of reports per minute
+    pub max_reports_per_minute: usize,
+}
+
+impl Default for HitchSettings {
+    fn default() -> Self {
+        HitchSettings {
+            frame_budget: Duration::from_millis(50),
+            percentile_threshold: None,
+            history_length: 30,
+            cooldown: Duration::from_secs(10),
+            max_reports_per_minute: 3,
+        }
+    }
+}
+
+/// Threshold relative to a percentile of the recent frame times
+pub struct PercentileThreshold {
+    /// The percentile of recent frame times to compare against (0.0 - 1.0)
+    pub percentile: f32,
+    /// A frame is a hitch if it takes longer than the percentile times this factor
+    pub factor: f32,
+}
+
+#[derive(Default)]
+struct HitchState {
+    frame_times: VecDeque<Duration>,
+    reports: VecDeque<Duration>,
+}
+
+impl HitchState {
+    /// The given percentile of the recent frame times, `None` without any frame times
+    fn percentile(&self, percentile: f32) -> Option<Duration> {
+        let mut frame_times: Vec<_> = self.frame_times.iter().copied().collect();
+        frame_times.sort_unstable();
+        let last = frame_times.len().checked_sub(1)?;
+        let index = (last as f32 * percentile.clamp(0., 1.)).round() as usize;
+
+        Some(frame_times[index])
+    }
+
+    fn is_hitch(&self, frame_time: Duration, settings: &HitchSettings) -> bool {
+        if frame_time > settings.frame_budget {
+            return true;
+        }
+        match &settings.percentile_threshold {
+            Some(threshold) if self.frame_times.len() >= settings.history_length => self
+                .percentile(threshold.percentile)
+                .map_or(false, |percentile| {
+                    frame_time > percentile.mul_f32(threshold.factor)
+                }),
+            _ => false,
+        }
+    }
+
+    /// Apply cooldown and rate limit and record the report if it is allowed
+    fn try_report(&mut self, now: Duration, settings: &HitchSettings) -> bool {
+        while matches!(self.reports.front(), Some(report) if now - *report > Duration::from_secs(60))
+        {
+            self.reports.pop_front();
+        }
+        if matches!(self.reports.back(), Some(last) if now - *last < settings.cooldown)
+            || self.reports.len() >= settings.max_reports_per_minute
+        {
+            return false;
+        }
+        self.reports.push_back(now);
+
+        true
+    }
+}
+
+fn detect_hitches(
+    sentry: Option<Res<Sentry>>,
+    time: Res<Time>,
+    settings: Res<HitchSettings>,
+    mut state: Local<HitchState>,
+) {
+    let frame_time = time.delta();
+    // The first frame has no delta
+    if frame_time.is_zero() {
+        return;
+    }
+    let now = Duration::from_secs_f64(time.seconds_since_startup());
+
+    if let Some(sentry) = &sentry {
+        if state.is_hitch(frame_time, &settings) && state.try_report(now, &settings) {
+            report_hitch(sentry.hub(), frame_time, &state.frame_times, &settings);
+        }
+    }
+
+    state.frame_times.push_back(frame_time);
+    while state.frame_times.len() > settings.history_length {
+        state.frame_times.pop_front();
+    }
+}
+
+/// Capture a hitch on a hub of its own
+///
+/// Systems run in parallel, so a scope pushed on the shared hub could leak into events captured
+/// by other systems at the same time.
+fn report_hitch(
+    hub: &Arc<Hub>,
+    frame_time: Duration,
+    frame_times: &VecDeque<Duration>,
+    settings: &HitchSettings,
+) {
+    let hub = Hub::new_from_top(hub);
+    hub.configure_scope(|scope| {
+        let mut context = Map::new();
+        context.insert("frame_time_ms".to_owned(), as_millis(frame_time).into());
+        context.insert(
+            "frame_budget_ms".to_owned(),
+            as_millis(settings.frame_budget).into(),
+        );
+        scope.set_context("hitch", Context::Other(context));
+    });
+    for (frames_ago, previous) in frame_times.iter().rev().enumerate().rev() {
+        hub.add_breadcrumb(Breadcrumb {
+            category: Some("frame".to_owned()),
+            message: Some(format!(
+                "Frame time {:.1} ms ({} frames ago)",
+                as_millis(*previous),
+                frames_ago + 1
+            )),
+            ..Default::default()
+        });
+    }
+    hub.capture_message(
+        &format!("Frame hitch of {:.1} ms", as_millis(frame_time)),
+        Level::Warning,
+    );
+}
+
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(frame_times_ms: &[u64]) -> HitchState {
+        HitchState {
+            frame_times: frame_times_ms
+                .iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect(),
+            reports: VecDeque::new(),
+        }
+    }
+
+    fn percentile_settings(history_length: usize) -> HitchSettings {
+        HitchSettings {
+            frame_budget: Duration::from_secs(1),
+            percentile_threshold: Some(PercentileThreshold {
+                percentile: 0.5,
+                factor: 2.,
+            }),
+            history_length,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn percentile_of_frame_times() {
+        let state = state(&[40, 10, 30, 20, 50]);
+        assert_eq!(state.percentile(0.), Some(Duration::from_millis(10)));
+        assert_eq!(state.percentile(0.5), Some(Duration::from_millis(30)));
+        assert_eq!(state.percentile(1.), Some(Duration::from_millis(50)));
+        assert_eq!(state.percentile(2.), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn percentile_without_frame_times() {
+        assert_eq!(state(&[]).percentile(0.5), None);
+    }
+
+    #[test]
+    fn hitch_relative_to_percentile() {
+        let settings = percentile_settings(3);
+        let state = state(&[10, 10, 10]);
+        assert!(!state.is_hitch(Duration::from_millis(20), &settings));
+        assert!(state.is_hitch(Duration::from_millis(21), &settings));
+    }
+
+    #[test]
+    fn no_percentile_hitch_without_history() {
+        let settings = percentile_settings(0);
+        assert!(!state(&[]).is_hitch(Duration::from_millis(100), &settings));
+        assert!(state(&[]).is_hitch(Duration::from_secs(2), &settings));
+    }
+
+    #[test]
+    fn reports_respect_cooldown() {
+        let settings = HitchSettings::default();
+        let mut state = state(&[]);
+        assert!(state.try_report(Duration::from_secs(0), &settings));
+        assert!(!state.try_report(Duration::from_secs(9), &settings));
+        assert!(state.try_report(Duration::from_secs(10), &settings));
+    }
+
+    #[test]
+    fn reports_respect_rate_limit() {
+        let settings = HitchSettings {
+            cooldown: Duration::ZERO,
+            max_reports_per_minute: 2,
+            ..Default::default()
+        };
+        let mut state = state(&[]);
+        assert!(state.try_report(Duration::from_secs(0), &settings));
+        assert!(state.try_report(Duration::from_secs(1), &settings));
+        assert!(!state.try_report(Duration::from_secs(2), &settings));
+        // Reports older than a minute no longer count
+        assert!(state.try_report(Duration::from_secs(61), &settings));
+        assert!(state.try_report(Duration::from_secs(62), &settings));
+        assert!(!state.try_report(Duration::from_secs(63), &settings));
+    }
+}
@@ -0,0 +1,105 @@
+use sentry::types::Scheme;
+use sentry::{ClientOptions, Envelope};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ureq::{Agent, AgentBuilder, Proxy};
+
+/// Maximum time to establish a connection to Sentry
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum time for a whole request, including the connection
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long to stop sending after Sentry rate limited a request without `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Sends serialized envelopes to the Sentry project of the configured DSN
+#[derive(Clone)]
+pub(crate) struct EnvelopeSender {
+    agent: Agent,
+    url: String,
+    auth: String,
+    /// Envelopes are not sent until this time after Sentry rate limited a request
+    rate_limited_until: Arc<Mutex<Option<Instant>>>,
+}
+
+/// Reasons an envelope could not be sent
+pub(crate) enum SendError {
+    /// Sentry could not be reached or is temporarily unavailable
+    Unavailable,
+    /// Sentry asked to stop sending for a while, the envelope should be dropped
+    RateLimited,
+    /// Sentry rejected the envelope, sending it again will not succeed
+    Rejected,
+}
+
+impl EnvelopeSender {
+    /// Returns `None` if no DSN is configured
+    pub(crate) fn new(options: &ClientOptions) -> Option<Self> {
+        let dsn = options.dsn.as_ref()?;
+        let proxy = match (dsn.scheme(), &options.http_proxy, &options.https_proxy) {
+            (Scheme::Https, _, Some(proxy)) | (_, Some(proxy), _) => Proxy::new(proxy).ok(),
+            _ => None,
+        };
+        let mut agent = AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT);
+        if let Some(proxy) = proxy {
+            agent = agent.proxy(proxy);
+        }
+
+        Some(EnvelopeSender {
+            agent: agent.build(),
+            url: dsn.envelope_api_url().to_string(),
+            auth: dsn.to_auth(Some(&options.user_agent)).to_string(),
+            rate_limited_until: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Serialize an envelope into the body of a request
+    pub(crate) fn serialize(envelope: &Envelope) -> Option<Vec<u8>> {
+        let mut body = Vec::new();
+        envelope.to_writer(&mut body).ok()?;
+        Some(body)
+    }
+
+    /// Send a serialized envelope, blocking until Sentry responded
+    ///
+    /// After Sentry responded with `429 Too Many Requests`, nothing is sent until the time given
+    /// by its `Retry-After` header passed.
+    pub(crate) fn send(&self, body: &[u8]) -> Result<(), SendError> {
+        if self.is_rate_limited() {
+            return Err(SendError::RateLimited);
+        }
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("X-Sentry-Auth", &self.auth)
+            .send_bytes(body);
+
+        match response {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(429, response)) => {
+                let retry_after = response
+                    .header("Retry-After")
+                    .and_then(|seconds| seconds.trim().parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_RETRY_AFTER);
+                if let Ok(mut rate_limited_until) = self.rate_limited_until.lock() {
+                    *rate_limited_until = Some(Instant::now() + retry_after);
+                }
+                Err(SendError::RateLimited)
+            }
+            Err(ureq::Error::Status(status, _)) if status >= 500 => Err(SendError::Unavailable),
+            Err(ureq::Error::Status(_, _)) => Err(SendError::Rejected),
+            Err(ureq::Error::Transport(_)) => Err(SendError::Unavailable),
+        }
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        match self.rate_limited_until.lock() {
+            Ok(rate_limited_until) => {
+                matches!(*rate_limited_until, Some(until) if Instant::now() < until)
+            }
+            Err(_) => false,
+        }
+    }
+}
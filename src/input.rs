@@ -0,0 +1,294 @@
+use crate::Sentry;
+use bevy::app::{App, Plugin};
+use bevy::core::Time;
+use bevy::input::gamepad::{GamepadEvent, GamepadEventType};
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::input::ElementState;
+use bevy::prelude::{EventReader, Local, Res};
+use sentry::protocol::Breadcrumb;
+use sentry::Hub;
+use std::time::Duration;
+
+const USER_BREADCRUMB: &str = "user";
+const INPUT_CATEGORY: &str = "ui.input";
+
+/// Records keyboard, mouse and gamepad input as Sentry breadcrumbs
+///
+/// Repeated presses of the same input are coalesced and the number of breadcrumbs per second is
+/// capped. Configure the plugin by inserting [`InputBreadcrumbSettings`]; the settings can be
+/// changed at runtime, e.g. to stop recording keys while the player enters text.
+///
+/// Requires Bevy's `InputPlugin`.
+#[derive(Default)]
+pub struct SentryInputBreadcrumbsPlugin;
+
+impl Plugin for SentryInputBreadcrumbsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBreadcrumbSettings>()
+            .add_system(record_input_breadcrumbs);
+    }
+}
+
+/// Settings for the [`SentryInputBreadcrumbsPlugin`]
+pub struct InputBreadcrumbSettings {
+    /// How key presses are recorded, [`KeyboardBreadcrumbs::Redacted`] by default so typed text
+    /// like chat messages or passwords is not sent to Sentry
+    pub keyboard: KeyboardBreadcrumbs,
+    /// Record mouse button presses
+    pub mouse: bool,
+    /// Record gamepad button presses and connection changes
+    pub gamepad: bool,
+    /// Maximum number of input breadcrumbs per second
+    pub max_per_second: usize,
+    /// Repeated presses of the same input within this window are coalesced
+    pub coalesce_window: Duration,
+}
+
+impl Default for InputBreadcrumbSettings {
+    fn default() -> Self {
+        InputBreadcrumbSettings {
+            keyboard: KeyboardBreadcrumbs::Redacted,
+            mouse: true,
+            gamepad: true,
+            max_per_second: 10,
+            coalesce_window: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Privacy filter for keyboard input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyboardBreadcrumbs {
+    /// Key presses are not recorded
+    Disabled,
+    /// Key presses are recorded without the pressed key
+    Redacted,
+    /// Key presses are recorded including the pressed key
+    Recorded,
+}
+
+#[derive(Default)]
+struct InputState {
+    last: Option<LastInput>,
+    second_start: f64,
+    recorded_this_second: usize,
+}
+
+struct LastInput {
+    message: String,
+    time: f64,
+    repeats: usize,
+}
+
+impl InputState {
+    fn record(&mut self, hub: &Hub, message: String, now: f64, settings: &InputBreadcrumbSettings) {
+        if let Some(last) = &mut self.last {
+            if last.message == message && now - last.time <= settings.coalesce_window.as_secs_f64()
+            {
+                last.time = now;
+                last.repeats += 1;
+                return;
+            }
+        }
+        self.flush_repeats(hub, now, settings);
+        self.add_breadcrumb(hub, message.clone(), now, settings);
+        self.last = Some(LastInput {
+            message,
+            time: now,
+            repeats: 0,
+        });
+    }
+
+    /// Record coalesced repeats once the input was not repeated within the coalesce window
+    fn flush_idle(&mut self, hub: &Hub, now: f64, settings: &InputBreadcrumbSettings) {
+        if matches!(&self.last, Some(last) if now - last.time > settings.coalesce_window.as_secs_f64())
+        {
+            self.flush_repeats(hub, now, settings);
+        }
+    }
+
+    fn flush_repeats(&mut self, hub: &Hub, now: f64, settings: &InputBreadcrumbSettings) {
+        if let Some(last) = self.last.take() {
+            if last.repeats > 0 {
+                self.add_breadcrumb(
+                    hub,
+                    format!("{} (repeated {} times)", last.message, last.repeats),
+                    now,
+                    settings,
+                );
+            }
+        }
+    }
+
+    fn add_breadcrumb(
+        &mut self,
+        hub: &Hub,
+        message: String,
+        now: f64,
+        settings: &InputBreadcrumbSettings,
+    ) {
+        if now - self.second_start >= 1. {
+            self.second_start = now;
+            self.recorded_this_second = 0;
+        }
+        if self.recorded_this_second >= settings.max_per_second {
+            return;
+        }
+        self.recorded_this_second += 1;
+
+        hub.add_breadcrumb(Breadcrumb {
+            ty: USER_BREADCRUMB.to_owned(),
+            category: Some(INPUT_CATEGORY.to_owned()),
+            message: Some(message),
+            ..Default::default()
+        });
+    }
+}
+
+fn record_input_breadcrumbs(
+    sentry: Option<Res<Sentry>>,
+    settings: Res<InputBreadcrumbSettings>,
+    time: Res<Time>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut state: Local<InputState>,
+) {
+    let hub = match &sentry {
+        Some(sentry) => sentry.hub(),
+        None => return,
+    };
+    let now = time.seconds_since_startup();
+
+    for event in keyboard_events.iter() {
+        if event.state != ElementState::Pressed {
+            continue;
+        }
+        let message = match (settings.keyboard, event.key_code) {
+            (KeyboardBreadcrumbs::Disabled, _) => continue,
+            (KeyboardBreadcrumbs::Redacted, _) => "Key pressed".to_owned(),
+            (KeyboardBreadcrumbs::Recorded, Some(key_code)) => {
+                format!("Key {:?} pressed", key_code)
+            }
+            (KeyboardBreadcrumbs::Recorded, None) => {
+                format!("Key with scan code {} pressed", event.scan_code)
+            }
+        };
+        state.record(hub, message, now, &settings);
+    }
+    for event in mouse_events.iter() {
+        if settings.mouse && event.state == ElementState::Pressed {
+            state.record(
+                hub,
+                format!("Mouse button {:?} pressed", event.button),
+                now,
+                &settings,
+            );
+        }
+    }
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        if !settings.gamepad {
+            continue;
+        }
+        let message = match event_type {
+            GamepadEventType::Connected => format!("Gamepad {} connected", gamepad.0),
+            GamepadEventType::Disconnected => format!("Gamepad {} disconnected", gamepad.0),
+            GamepadEventType::ButtonChanged(button, value) if *value > 0.5 => {
+                format!("Gamepad {} button {:?} pressed", gamepad.0, button)
+            }
+            _ => continue,
+        };
+        state.record(hub, message, now, &settings);
+    }
+
+    state.flush_idle(hub, now, &settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sentry::test::with_captured_events;
+    use sentry::Level;
+
+    /// Messages of the breadcrumbs recorded with a fresh input state
+    fn breadcrumbs(record: impl FnOnce(&mut InputState, &Hub)) -> Vec<String> {
+        let events = with_captured_events(|| {
+            let hub = Hub::current();
+            record(&mut InputState::default(), &hub);
+            hub.capture_message("Input", Level::Info);
+        });
+        events[0]
+            .breadcrumbs
+            .values
+            .iter()
+            .filter_map(|breadcrumb| breadcrumb.message.clone())
+            .collect()
+    }
+
+    fn press(state: &mut InputState, hub: &Hub, input: &str, now: f64) {
+        state.record(
+            hub,
+            input.to_owned(),
+            now,
+            &InputBreadcrumbSettings::default(),
+        );
+    }
+
+    #[test]
+    fn coalesces_repeats_within_window() {
+        let breadcrumbs = breadcrumbs(|state, hub| {
+            press(state, hub, "Key pressed", 0.);
+            press(state, hub, "Key pressed", 0.4);
+            press(state, hub, "Key pressed", 0.8);
+            state.flush_idle(hub, 1.0, &InputBreadcrumbSettings::default());
+            assert!(state.last.is_some());
+            state.flush_idle(hub, 1.4, &InputBreadcrumbSettings::default());
+        });
+        assert_eq!(
+            breadcrumbs,
+            vec!["Key pressed", "Key pressed (repeated 2 times)"]
+        );
+    }
+
+    #[test]
+    fn records_repeats_outside_window() {
+        let breadcrumbs = breadcrumbs(|state, hub| {
+            press(state, hub, "Key pressed", 0.);
+            press(state, hub, "Key pressed", 0.6);
+            state.flush_idle(hub, 2., &InputBreadcrumbSettings::default());
+        });
+        assert_eq!(breadcrumbs, vec!["Key pressed", "Key pressed"]);
+    }
+
+    #[test]
+    fn other_input_flushes_repeats() {
+        let breadcrumbs = breadcrumbs(|state, hub| {
+            press(state, hub, "Key pressed", 0.);
+            press(state, hub, "Key pressed", 0.1);
+            press(state, hub, "Mouse button Left pressed", 0.2);
+        });
+        assert_eq!(
+            breadcrumbs,
+            vec![
+                "Key pressed",
+                "Key pressed (repeated 1 times)",
+                "Mouse button Left pressed"
+            ]
+        );
+    }
+
+    #[test]
+    fn caps_breadcrumbs_per_second() {
+        let settings = InputBreadcrumbSettings {
+            max_per_second: 2,
+            ..Default::default()
+        };
+        let breadcrumbs = breadcrumbs(|state, hub| {
+            for (input, now) in [("A", 0.), ("B", 0.1), ("C", 0.2), ("D", 1.1)] {
+                state.record(hub, input.to_owned(), now, &settings);
+            }
+        });
+        assert_eq!(breadcrumbs, vec!["A", "B", "D"]);
+    }
+}
@@ -11,14 +11,13 @@
 //!     app.add_plugins(DefaultPlugins)
 //! #    */
 //! #    app.add_plugins(MinimalPlugins)
-//!     app.insert_resource(SentryConfig::from_options((
+//!         .add_plugin(SentryPlugin::new(SentryConfig::from_options((
 //!             "https://examplePublicKey@o0.ingest.sentry.io/0",
 //!             ClientOptions {
 //!                 release: release_name!(),
 //!                 ..Default::default()
 //!             },
-//!         )))
-//!         .add_plugin(SentryPlugin)
+//!         ))))
 //! #        .set_runner(|mut app| app.schedule.run(&mut app.world))
 //!         .run();
 //! }
@@ -30,65 +29,378 @@
 /// Reexported sentry crate
 pub use sentry::*;
 
-use bevy::app::{App, Plugin};
-use bevy::ecs::system::Resource;
-use bevy::log::error;
-use bevy::prelude::{Res, SystemSet};
-use sentry::protocol::Value;
-use std::collections::BTreeMap;
-use std::marker::PhantomData;
+pub use bevy_sentry_derive::SentryContext;
+#[doc(hidden)]
+pub use context::serialize_context_field;
+pub use context::SentryContext;
+pub use errors::{capture_errors, ReportErrors};
+pub use event::SentryEvent;
+pub use hitch::{HitchSettings, PercentileThreshold, SentryHitchPlugin};
+pub use input::{InputBreadcrumbSettings, KeyboardBreadcrumbs, SentryInputBreadcrumbsPlugin};
+pub use logging::{LogForwarding, LogLevels, SentryLogPlugin};
+pub use settings::SentrySettings;
+pub use task::spawn_with_sentry;
+pub use tracking::SentryTracked;
+
+mod breadcrumbs;
+mod context;
+mod diagnostics;
+mod env;
+mod errors;
+mod event;
+mod exit;
+mod hitch;
+mod http;
+mod input;
+mod logging;
+mod offline;
+mod panics;
+mod performance;
+mod resources;
+mod runtime;
+mod session;
+mod settings;
+mod state;
+mod task;
+mod task_pool;
+mod tracking;
+
+use crate::breadcrumbs::{debug_breadcrumb, record_event_breadcrumbs, BreadcrumbMapper};
+use crate::context::{
+    apply_registered_context, apply_registered_serialized_context, remove_context,
+    set_sentry_context, set_serialized_context, ContextRegistration, RegisteredContexts,
+};
+use crate::diagnostics::{setup_diagnostics, DiagnosticsSettings};
+use crate::env::{apply_override, arg_overrides, env_overrides};
+use crate::event::capture_sentry_events;
+use crate::exit::setup_exit_flush;
+use crate::offline::{setup_offline_cache, OfflineCacheSettings};
+use crate::panics::{capture_panics_with_main_hub, tag_system_panics};
+use crate::resources::{
+    register_resource_snapshot, setup_resource_snapshots, ResourceSnapshotRegistration,
+};
+use crate::runtime::{setup_runtime_context, RuntimeContextSettings};
+use crate::session::{record_started_session, report_abnormal_session, setup_session_tracking};
+use crate::settings::setup_settings_asset;
+use crate::state::{track_state, TrackedState};
+use crate::task_pool::setup_task_pool_transport;
+use crate::tracking::{
+    setup_entity_tracking, DEFAULT_TRACKED_ENTITY_INTERVAL, DEFAULT_TRACKED_ENTITY_LIMIT,
+};
+use bevy::app::{App, CoreStage, Plugin};
+use bevy::diagnostic::DiagnosticId;
+use bevy::ecs::schedule::{ParallelSystemDescriptorCoercion, StageLabel, StateData, SystemLabel};
+use bevy::ecs::system::{IntoSystem, Resource};
+use bevy::ecs::world::World;
+use bevy::log::{error, info};
+use bevy::reflect::Reflect;
+use bevy::tasks::IoTaskPool;
+use sentry::protocol::SessionStatus;
+use serde::Serialize;
+use std::any::type_name;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// [Sentry.io](https://sentry.io) integration for Bevy applications
-pub struct SentryPlugin;
+///
+/// Create the plugin with [`SentryPlugin::new`] or [`SentryPlugin::disabled`]. The plugin
+/// created by `SentryPlugin::default()` takes its configuration from the `SentryConfig`
+/// resource instead.
+///
+/// The [`Sentry`] resource and [`SentryApp`] methods are available even if reporting is
+/// disabled, either explicitly or because no DSN is configured.
+#[derive(Default)]
+pub struct SentryPlugin {
+    config: Mutex<Option<SentryConfig>>,
+}
+
+impl SentryPlugin {
+    /// Report to Sentry with the given configuration
+    ///
+    /// A `SentryConfig` resource is ignored. Create the [`SentryLogPlugin`] with
+    /// [`SentryLogPlugin::new`] from the same configuration before passing it here.
+    pub fn new(config: SentryConfig) -> Self {
+        SentryPlugin {
+            config: Mutex::new(Some(config)),
+        }
+    }
+
+    /// Do not report anything to Sentry
+    pub fn disabled() -> Self {
+        let mut config = SentryConfig::from_options(ClientOptions::default());
+        config.enabled = false;
+        SentryPlugin::new(config)
+    }
+}
 
 impl Plugin for SentryPlugin {
     fn build(&self, app: &mut App) {
-        if let Some(configuration) = app.world.remove_resource::<SentryConfig>() {
-            app.insert_resource(Sentry {
-                guard: init(configuration.options),
-            });
+        app.add_event::<SentryEvent>();
+        let configuration = self
+            .config
+            .lock()
+            .ok()
+            .and_then(|mut config| config.take())
+            .or_else(|| app.world.remove_resource::<SentryConfig>());
+        let sentry = match configuration {
+            Some(configuration) if configuration.enabled => init_sentry(app, configuration),
+            Some(_) => {
+                info!("Sentry is disabled");
+                Sentry::disabled()
+            }
+            None => {
+                error!("Please supply a `SentryConfig` with `SentryPlugin::new` or as resource");
+                Sentry::disabled()
+            }
+        };
+        if let Some(contexts) = app.world.get_resource::<RegisteredContexts>() {
+            contexts.apply(&app.world, sentry.hub());
+        }
+        // Ordering after a label without systems makes Bevy warn on every start
+        let capture = capture_sentry_events.label(SentrySystem::Capture);
+        let capture = if app.world.contains_resource::<SnapshotSystems>() {
+            capture.after(SentrySystem::Snapshot)
         } else {
-            error!("Please supply a `SentryConfig` as resource");
+            capture
+        };
+        app.insert_resource(sentry)
+            .add_system_to_stage(CoreStage::Last, capture);
+    }
+}
+
+/// Initialize the Sentry client and set up all integrations
+fn init_sentry(app: &mut App, mut configuration: SentryConfig) -> Sentry {
+    tag_system_panics(&mut configuration.options);
+    let io_task_pool = app.world.get_resource::<IoTaskPool>();
+    match (configuration.io_task_pool_transport, io_task_pool) {
+        (true, Some(io_task_pool)) => setup_task_pool_transport(
+            &mut configuration.options,
+            io_task_pool.0.clone(),
+            configuration.offline_cache,
+        ),
+        (true, None) => {
+            error!("The IoTaskPool transport requires Bevy's `CorePlugin`");
         }
+        (false, _) => {
+            if let Some(offline_cache) = configuration.offline_cache {
+                setup_offline_cache(&mut configuration.options, offline_cache);
+            }
+        }
+    }
+    let unfinished_session =
+        setup_session_tracking(&mut configuration.options, configuration.session_file);
+    // `init` binds the client to the hub of the current thread, which is only the main hub if
+    // this thread used Sentry first
+    let hub = Hub::main();
+    let guard = Hub::run(hub.clone(), || init(configuration.options));
+    // The settings asset may still provide a DSN
+    if !guard.is_enabled() && configuration.settings_asset.is_none() {
+        info!("Sentry is disabled, no DSN is configured");
+        return Sentry {
+            guard: Some(guard),
+            hub,
+        };
+    }
+    if let Some(session) = unfinished_session {
+        report_abnormal_session(&guard, session);
+    }
+    record_started_session(&guard);
+    capture_panics_with_main_hub();
+    if let Some(path) = &configuration.settings_asset {
+        setup_settings_asset(app, guard.options(), path);
+    }
+    setup_entity_tracking(
+        app,
+        &hub,
+        configuration.tracked_entity_limit,
+        configuration.tracked_entity_interval,
+    );
+    setup_resource_snapshots(app, &hub, &configuration.resource_snapshots);
+    setup_diagnostics(app, &hub, configuration.diagnostics);
+    setup_runtime_context(app, &hub, configuration.runtime_context);
+    setup_exit_flush(app);
+
+    Sentry {
+        guard: Some(guard),
+        hub,
     }
 }
 
+/// Labels of `bevy_sentry` systems
+#[derive(SystemLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SentrySystem {
+    /// Systems collecting data from the world that is attached to events
+    Snapshot,
+    /// Systems capturing events
+    Capture,
+}
+
+/// Marks that systems with the label [`SentrySystem::Snapshot`] were added
+#[derive(Default)]
+struct SnapshotSystems;
+
+/// Add a system collecting data from the world that is attached to events
+///
+/// Systems capturing events run after all snapshot systems.
+pub(crate) fn add_snapshot_system<Params>(app: &mut App, system: impl IntoSystem<(), (), Params>) {
+    app.init_resource::<SnapshotSystems>()
+        .add_system_to_stage(CoreStage::Last, system.label(SentrySystem::Snapshot));
+}
+
 /// App extension trait for sentry integration
 pub trait SentryApp {
-    fn register_context<T: Resource>(
+    /// Register a new Sentry context
+    ///
+    /// The context is updated every time the resource `T` changes and removed from Sentry's
+    /// scope when the resource is removed. If the resource already exists, it is attached as soon
+    /// as Sentry is initialized, no matter if the [`SentryPlugin`] is added before or after.
+    fn register_context<T: SentryContext>(&mut self) -> &mut Self;
+
+    /// Register a resource that is serialized into a Sentry context
+    ///
+    /// The resource `T` is serialized with `serde` every time it changes and attached to
+    /// Sentry events under the given key. Resources that do not serialize to a map are
+    /// reported under the field `value`. Removing the resource removes the context.
+    fn register_serialized_context<T: Resource + Serialize>(
+        &mut self,
+        key: &'static str,
+    ) -> &mut Self;
+
+    /// Stop reporting the resource `T` as Sentry context
+    ///
+    /// The context is removed from Sentry's scope right away. It can be registered again later.
+    fn unregister_context<T: Resource>(&mut self) -> &mut Self;
+
+    /// Record transitions of the state `S` as navigation breadcrumbs
+    ///
+    /// Events are tagged with the current value of the state as `state.<type name>`.
+    /// Any number of state types can be tracked at the same time. Tracking the same state type
+    /// again has no effect.
+    fn track_state<S: StateData>(&mut self) -> &mut Self;
+
+    /// Record every event of type `E` as Sentry breadcrumb
+    ///
+    /// The given closure converts the events to breadcrumbs. Calling this again for the same
+    /// event type replaces the closure.
+    fn add_sentry_breadcrumbs<E: Resource>(
         &mut self,
-        initial_value: Option<SentryContext<T>>,
+        mapper: impl Fn(&E) -> Breadcrumb + Send + Sync + 'static,
     ) -> &mut Self;
+
+    /// Record every event of type `E` as Sentry breadcrumb using its `Debug` representation
+    ///
+    /// The breadcrumbs have the event's type name as category.
+    fn add_debug_sentry_breadcrumbs<E: Resource + Debug>(&mut self) -> &mut Self;
 }
 
 impl SentryApp for App {
-    /// Register a new Sentry context
-    ///
-    /// If you pass an initial value, it will be configures as soon as Sentry is initialized.
-    /// You can later update the context by changing the resource `SentryContext<T>`
-    fn register_context<T: Resource>(
+    fn register_context<T: SentryContext>(&mut self) -> &mut Self {
+        if !activate_context_registration::<T>(self) {
+            self.insert_resource(ContextRegistration::<T>::new(T::key()))
+                .add_system(set_sentry_context::<T>);
+            add_registered_context(self, apply_registered_context::<T>);
+        }
+        if let Some(sentry) = self.world.get_resource::<Sentry>() {
+            apply_registered_context::<T>(&self.world, sentry.hub());
+        }
+
+        self
+    }
+
+    fn register_serialized_context<T: Resource + Serialize>(
         &mut self,
-        initial_value: Option<SentryContext<T>>,
+        key: &'static str,
     ) -> &mut Self {
-        self.add_system(set_sentry_context::<T>);
-        if let Some(context) = initial_value {
-            configure_scope(|scope| {
-                scope.set_context(
-                    context.get_key(),
-                    protocol::Context::Other(*context.get_context().clone()),
-                );
-            });
+        if !activate_context_registration::<T>(self) {
+            self.insert_resource(ContextRegistration::<T>::new(key))
+                .add_system(set_serialized_context::<T>);
+            add_registered_context(self, apply_registered_serialized_context::<T>);
+        }
+        if let Some(sentry) = self.world.get_resource::<Sentry>() {
+            apply_registered_serialized_context::<T>(&self.world, sentry.hub());
+        }
+
+        self
+    }
+
+    fn unregister_context<T: Resource>(&mut self) -> &mut Self {
+        if let Some(mut registration) = self.world.get_resource_mut::<ContextRegistration<T>>() {
+            registration.active = false;
+            let key = registration.key;
+            if let Some(sentry) = self.world.get_resource::<Sentry>() {
+                remove_context(sentry.hub(), key);
+            }
         }
 
         self
     }
+
+    fn track_state<S: StateData>(&mut self) -> &mut Self {
+        if self.world.contains_resource::<TrackedState<S>>() {
+            return self;
+        }
+        self.init_resource::<TrackedState<S>>()
+            .add_system_to_stage(CoreStage::Last, track_state::<S>)
+    }
+
+    fn add_sentry_breadcrumbs<E: Resource>(
+        &mut self,
+        mapper: impl Fn(&E) -> Breadcrumb + Send + Sync + 'static,
+    ) -> &mut Self {
+        let registered = self.world.contains_resource::<BreadcrumbMapper<E>>();
+        self.insert_resource(BreadcrumbMapper::new(mapper));
+        if !registered {
+            self.add_system(record_event_breadcrumbs::<E>);
+        }
+
+        self
+    }
+
+    fn add_debug_sentry_breadcrumbs<E: Resource + Debug>(&mut self) -> &mut Self {
+        self.add_sentry_breadcrumbs(debug_breadcrumb::<E>)
+    }
+}
+
+/// Reactivate an existing registration of the context `T`
+///
+/// Returns `false` if the context was never registered.
+fn activate_context_registration<T: Resource>(app: &mut App) -> bool {
+    if let Some(mut registration) = app.world.get_resource_mut::<ContextRegistration<T>>() {
+        registration.active = true;
+        return true;
+    }
+
+    false
+}
+
+/// Remember to attach a context once Sentry is initialized
+fn add_registered_context(app: &mut App, apply: fn(&World, &Hub)) {
+    app.world
+        .get_resource_or_insert_with(RegisteredContexts::default)
+        .add(apply);
 }
 
 /// Configuration resource for `bevy_sentry`
 ///
-/// This resource is removed from the app during plugin initialization!
+/// Pass it to [`SentryPlugin::new`] or insert it as resource before adding
+/// `SentryPlugin::default()`. The resource is removed from the app during plugin initialization!
 pub struct SentryConfig {
     options: ClientOptions,
+    log_levels: LogLevels,
+    tracked_entity_limit: usize,
+    tracked_entity_interval: Duration,
+    resource_snapshots: Vec<ResourceSnapshotRegistration>,
+    performance_monitoring: bool,
+    instrumented_stages: Vec<String>,
+    diagnostics: DiagnosticsSettings,
+    runtime_context: RuntimeContextSettings,
+    session_file: Option<PathBuf>,
+    offline_cache: Option<OfflineCacheSettings>,
+    io_task_pool_transport: bool,
+    settings_asset: Option<String>,
+    enabled: bool,
 }
 
 impl SentryConfig {
@@ -99,62 +411,402 @@ impl SentryConfig {
     {
         SentryConfig {
             options: options.into(),
+            log_levels: LogLevels::default(),
+            tracked_entity_limit: DEFAULT_TRACKED_ENTITY_LIMIT,
+            tracked_entity_interval: DEFAULT_TRACKED_ENTITY_INTERVAL,
+            resource_snapshots: vec![],
+            performance_monitoring: false,
+            instrumented_stages: vec![],
+            diagnostics: DiagnosticsSettings::default(),
+            runtime_context: RuntimeContextSettings::default(),
+            session_file: None,
+            offline_cache: None,
+            io_task_pool_transport: false,
+            settings_asset: None,
+            enabled: true,
+        }
+    }
+
+    /// Build options for `bevy_sentry` from environment variables
+    ///
+    /// See [`with_env_overrides`](Self::with_env_overrides) for the supported variables.
+    pub fn from_env() -> Self {
+        SentryConfig::from_options(ClientOptions::default()).with_env_overrides()
+    }
+
+    /// Override options with the values of environment variables
+    ///
+    /// Supported are `SENTRY_DSN`, `SENTRY_ENVIRONMENT`, `SENTRY_RELEASE`, `SENTRY_SAMPLE_RATE`,
+    /// `SENTRY_TRACES_SAMPLE_RATE` and `SENTRY_DISABLED`. Setting `SENTRY_DISABLED` to `true`
+    /// turns off reporting to Sentry completely. Invalid values are ignored with a warning.
+    pub fn with_env_overrides(mut self) -> Self {
+        for (setting, value) in env_overrides() {
+            apply_override(&mut self, setting, &value);
+        }
+        self
+    }
+
+    /// Override options with the command-line arguments of the process
+    ///
+    /// The same options as for [`with_env_overrides`](Self::with_env_overrides) are supported
+    /// as `--sentry-<option> <value>` or `--sentry-<option>=<value>` arguments, e.g.
+    /// `--sentry-environment=qa` or `--sentry-disabled`. Call this after `with_env_overrides` to
+    /// give arguments precedence over environment variables. Arguments that are not valid
+    /// Unicode are ignored.
+    pub fn with_arg_overrides(mut self) -> Self {
+        let args = std::env::args_os()
+            .skip(1)
+            .filter_map(|arg| arg.into_string().ok());
+        for (setting, value) in arg_overrides(args) {
+            apply_override(&mut self, setting, &value);
+        }
+        self
+    }
+
+    /// Configure how log records of the given level are forwarded to Sentry
+    ///
+    /// This only has an effect if the [`SentryLogPlugin`] is used.
+    pub fn with_log_level(mut self, level: bevy::log::Level, forwarding: LogForwarding) -> Self {
+        self.log_levels.set(level, forwarding);
+        self
+    }
+
+    /// Set the maximum number of [`SentryTracked`] entities attached to an event
+    ///
+    /// Defaults to 10. Setting the limit to 0 disables entity tracking.
+    pub fn with_tracked_entity_limit(mut self, limit: usize) -> Self {
+        self.tracked_entity_limit = limit;
+        self
+    }
+
+    /// Set the time between two snapshots of [`SentryTracked`] entities
+    ///
+    /// Defaults to 1 second. Snapshots clone all reflected components of the tracked entities
+    /// in an exclusive system, so shorter intervals cost more frame time. Events report the
+    /// state of the latest snapshot.
+    pub fn with_tracked_entity_interval(mut self, interval: Duration) -> Self {
+        self.tracked_entity_interval = interval;
+        self
+    }
+
+    /// Attach a reflected snapshot of the resource `T` to every Sentry event
+    ///
+    /// The resource is cloned through reflection whenever it changes, but only serialized
+    /// when an event is sent. Snapshots of all registered resources are reported in the
+    /// `bevy.resources` context.
+    ///
+    /// Sentry's hooks run without access to the `World`, so the snapshot has to be taken in a
+    /// system. A resource that is mutated every frame is cloned every frame, so only register
+    /// resources that change rarely or are cheap to clone.
+    pub fn with_resource_snapshot<T: Resource + Reflect>(mut self) -> Self {
+        self.resource_snapshots
+            .push(register_resource_snapshot::<T>);
+        self
+    }
+
+    /// Enable performance monitoring with the given sample rate (0.0 - 1.0)
+    ///
+    /// Every sampled frame becomes a Sentry transaction with the executed systems as child spans.
+    /// Use [`with_instrumented_stage`](Self::with_instrumented_stage) to create transactions for
+    /// runs of single stages instead.
+    ///
+    /// Performance data is taken from Bevy's tracing spans. This requires the [`SentryLogPlugin`]
+    /// and the `trace` feature of `bevy_sentry`.
+    pub fn with_performance_monitoring(mut self, sample_rate: f32) -> Self {
+        self.options.traces_sample_rate = sample_rate;
+        self.performance_monitoring = true;
+        self
+    }
+
+    /// Create a Sentry transaction for every sampled run of the given stage
+    ///
+    /// This only has an effect if [performance monitoring](Self::with_performance_monitoring)
+    /// is enabled.
+    pub fn with_instrumented_stage(mut self, stage: impl StageLabel) -> Self {
+        self.instrumented_stages.push(format!("{:?}", stage));
+        self
+    }
+
+    /// Enable or disable attaching Bevy's `Diagnostics` to Sentry events
+    ///
+    /// Enabled by default. The latest smoothed value of every diagnostic is reported in the
+    /// `bevy.diagnostics` context. This requires diagnostics to be registered, e.g. through
+    /// Bevy's `FrameTimeDiagnosticsPlugin`.
+    pub fn with_diagnostics(mut self, enabled: bool) -> Self {
+        self.diagnostics.enabled = enabled;
+        self
+    }
+
+    /// Only report the diagnostics with the given ids
+    pub fn with_diagnostics_allowlist(mut self, allowlist: Vec<DiagnosticId>) -> Self {
+        self.diagnostics.allowlist = Some(allowlist);
+        self
+    }
+
+    /// Additionally send diagnostics aggregated over the given interval to Sentry
+    ///
+    /// Minimum, maximum, average and count of every reported diagnostic are sent as data of a
+    /// `bevy.diagnostics` transaction, not as Sentry measurements. These transactions are
+    /// sampled with the traces sample rate of the client options, which defaults to 0.0, so
+    /// none are sent unless it is raised. Aggregation requires Bevy's `CorePlugin`.
+    pub fn with_diagnostics_aggregation(mut self, interval: Duration) -> Self {
+        self.diagnostics.aggregation_interval = Some(interval);
+        self
+    }
+
+    /// Set how long to wait for pending events to be sent when the app exits
+    ///
+    /// Pending events are sent when the app sends `AppExit` and flushed when a window is
+    /// requested to close. Defaults to 2 seconds.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.options.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Track release health sessions in the given mode
+    ///
+    /// In [`SessionMode::Application`], a session is started when Sentry is initialized. It ends
+    /// as exited when the app exits, as crashed on a panic and is reported as abnormal on the
+    /// next start if the process died without either, which is detected through a
+    /// [session file](Self::with_session_file). In [`SessionMode::Request`], sessions
+    /// are started and ended manually through the [`Sentry`] resource, e.g. for every match.
+    ///
+    /// Sessions require a release to be set in the client options.
+    pub fn with_session_tracking(mut self, mode: SessionMode) -> Self {
+        self.options.auto_session_tracking = true;
+        self.options.session_mode = mode;
+        self
+    }
+
+    /// Set the file used to detect sessions that ended abnormally
+    ///
+    /// Defaults to a file named after the release in the temporary directory. Instances of the
+    /// same release running at the same time share this file and overwrite each other's session,
+    /// so give each instance its own file if that happens, e.g. one per save slot or profile.
+    pub fn with_session_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.session_file = Some(file.into());
+        self
+    }
+
+    /// Cache events in the given directory if they can not be sent
+    ///
+    /// Cached events are sent on the next start or as soon as sending succeeds again. By
+    /// default, at most 10 MiB of events that are up to 30 days old are kept. This replaces
+    /// the transport set in the client options with an HTTP transport.
+    pub fn with_offline_cache(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.offline_cache = Some(OfflineCacheSettings::new(directory.into()));
+        self
+    }
+
+    /// Limit the total size in bytes and the age of events in the offline cache
+    ///
+    /// This only has an effect if the [offline cache](Self::with_offline_cache) is enabled.
+    pub fn with_offline_cache_limits(mut self, max_size: u64, max_age: Duration) -> Self {
+        if let Some(offline_cache) = &mut self.offline_cache {
+            offline_cache.max_size = max_size;
+            offline_cache.max_age = max_age;
         }
+        self
+    }
+
+    /// Send events in a task on Bevy's `IoTaskPool` instead of a separate thread
+    ///
+    /// This replaces the transport set in the client options with an HTTP transport. Events are
+    /// queued and sent one after another by a single task, which blocks one thread of the pool
+    /// while it waits for Sentry. Events are still cached if the
+    /// [offline cache](Self::with_offline_cache) is enabled.
+    pub fn with_io_task_pool_transport(mut self) -> Self {
+        self.io_task_pool_transport = true;
+        self
+    }
+
+    /// Load [`SentrySettings`] from the given asset, e.g. `"config/game.sentry.ron"`
+    ///
+    /// The settings override the client options once the asset is loaded, events captured before
+    /// use the options of this config. With `watch_for_changes` enabled in Bevy's
+    /// `AssetServerSettings`, changes to the file apply while the app is running. This requires
+    /// Bevy's `AssetPlugin` to be added before the [`SentryPlugin`].
+    pub fn with_settings_asset(mut self, path: impl Into<String>) -> Self {
+        self.settings_asset = Some(path.into());
+        self
+    }
+
+    /// Enable or disable the automatic `bevy` context
+    ///
+    /// Enabled by default. The context contains the Bevy and `bevy_sentry` versions, the frame
+    /// count and the time since startup. It does not list the added plugins, because Bevy 0.6
+    /// does not keep track of the plugins added to an `App`.
+    pub fn with_bevy_context(mut self, enabled: bool) -> Self {
+        self.runtime_context.enabled = enabled;
+        self
+    }
+
+    /// Enable or disable reporting the primary window in the `bevy` context
+    ///
+    /// Enabled by default. Size, scale factor and mode of the primary window are reported.
+    pub fn with_window_context(mut self, enabled: bool) -> Self {
+        self.runtime_context.window = enabled;
+        self
     }
 }
 
 /// Runtime data for Sentry integration
 pub struct Sentry {
-    #[allow(dead_code)]
-    guard: ClientInitGuard,
+    guard: Option<ClientInitGuard>,
+    hub: Arc<Hub>,
 }
 
-/// A sentry context
-///
-/// The string value mapping will be passed to Sentry as a custom context with every event.
-/// See https://docs.sentry.io/product/sentry-basics/enrich-data/#types-of-data
-pub struct SentryContext<T> {
-    key: &'static str,
-    context: Box<BTreeMap<String, Value>>,
-    _phantom_data: PhantomData<T>,
-}
+impl Sentry {
+    fn disabled() -> Self {
+        Sentry {
+            guard: None,
+            hub: Hub::main(),
+        }
+    }
 
-impl<T> DynamicSentryContext for SentryContext<T> {
-    fn get_key(&self) -> &str {
-        self.key
+    /// Whether events are sent to Sentry
+    pub fn is_enabled(&self) -> bool {
+        self.hub
+            .client()
+            .map_or(false, |client| client.is_enabled())
     }
 
-    fn get_context(&self) -> Box<BTreeMap<String, Value>> {
-        self.context.clone()
+    /// The hub all data of `bevy_sentry` is reported to
+    ///
+    /// This is Sentry's main hub. Bevy runs systems on the threads of its task pools, which have
+    /// their own hubs that miss contexts, tags and breadcrumbs reported by `bevy_sentry`.
+    /// Capture events from systems through this hub or by sending a [`SentryEvent`].
+    pub fn hub(&self) -> &Arc<Hub> {
+        &self.hub
     }
-}
 
-impl<T> SentryContext<T> {
-    /// Create a new sentry context
-    pub fn new(key: &'static str, context: BTreeMap<String, Value>) -> Self {
-        SentryContext {
-            key,
-            context: Box::new(context),
-            _phantom_data: PhantomData::default(),
-        }
+    /// Start a new release health session
+    ///
+    /// A running session is ended as exited, e.g. when returning to the main menu.
+    pub fn start_session(&self) {
+        self.hub.start_session();
     }
-}
 
-trait DynamicSentryContext {
-    fn get_key(&self) -> &str;
+    /// End the running release health session with the given status
+    pub fn end_session(&self, status: SessionStatus) {
+        self.hub.end_session_with_status(status);
+    }
+}
 
-    fn get_context(&self) -> Box<BTreeMap<String, Value>>;
+/// Type name without module path, e.g. `AppState` for `my_game::state::AppState`
+pub(crate) fn short_type_name<T>() -> &'static str {
+    let type_name = type_name::<T>();
+    type_name.rsplit("::").next().unwrap_or(type_name)
 }
 
-fn set_sentry_context<T: Resource>(context: Option<Res<SentryContext<T>>>) {
-    if let Some(context) = context {
-        if context.is_changed() {
-            configure_scope(|scope| {
-                scope.set_context(
-                    context.key,
-                    sentry::protocol::Context::Other(*context.context.clone()),
-                );
-            });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::{Local, MinimalPlugins, Res};
+    use sentry::protocol::Context;
+    use sentry::test::TestTransport;
+    use std::sync::PoisonError;
+
+    /// Held by tests binding a client to the main hub, so their events do not mix
+    static MAIN_HUB: Mutex<()> = Mutex::new(());
+
+    /// Configuration sending events to the returned transport
+    fn test_config() -> (SentryConfig, Arc<TestTransport>) {
+        let transport = TestTransport::new();
+        let options = ClientOptions {
+            dsn: "https://public@sentry.invalid/1".into_dsn().unwrap(),
+            transport: Some(Arc::new(transport.clone())),
+            ..Default::default()
+        };
+        let config = SentryConfig::from_options(options)
+            .with_diagnostics(false)
+            .with_bevy_context(false);
+
+        (config, transport)
+    }
+
+    /// Private imports in the crate root must not shadow the items re-exported from `sentry`
+    #[test]
+    fn sentry_items_are_reexported() {
+        let _: crate::Level = sentry::Level::Warning;
+        let _: crate::Breadcrumb = sentry::Breadcrumb::default();
+        // Touching the main hub here would race with the tests binding a client to it
+        let _: Option<Arc<crate::Hub>> = None::<Arc<sentry::Hub>>;
+    }
+
+    #[derive(Serialize)]
+    struct SaveGame {
+        slot: u32,
+    }
+
+    /// A context set in one system is attached to events captured in another, parallel system
+    #[test]
+    fn contexts_are_shared_between_systems() {
+        fn capture_in_second_frame(sentry: Res<Sentry>, mut frame: Local<u32>) {
+            *frame += 1;
+            if *frame == 2 {
+                sentry
+                    .hub()
+                    .capture_message("Something happened", Level::Warning);
+            }
+        }
+
+        let _main_hub = MAIN_HUB.lock().unwrap_or_else(PoisonError::into_inner);
+        let (config, transport) = test_config();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .register_serialized_context::<SaveGame>("save_game")
+            .add_plugin(SentryPlugin::new(config))
+            .insert_resource(SaveGame { slot: 1 })
+            .add_system(capture_in_second_frame);
+        app.update();
+        app.update();
+
+        let events = transport.fetch_and_clear_events();
+        assert_eq!(events.len(), 1);
+        match events[0].contexts.get("save_game") {
+            Some(Context::Other(context)) => assert_eq!(context.get("slot"), Some(&1.into())),
+            context => panic!("Unexpected context {:?}", context),
         }
     }
+
+    #[derive(Serialize)]
+    struct Difficulty {
+        level: u32,
+    }
+
+    /// Run a frame and check if an event captured afterwards has the context `key`
+    fn has_context(app: &mut App, transport: &TestTransport, key: &str) -> bool {
+        app.update();
+        let sentry = app.world.get_resource::<Sentry>().unwrap();
+        sentry.hub().capture_message("Test", Level::Info);
+        let events = transport.fetch_and_clear_events();
+        assert_eq!(events.len(), 1);
+        events[0].contexts.contains_key(key)
+    }
+
+    /// Contexts are removed with their resource or registration and attached again afterwards
+    #[test]
+    fn contexts_follow_resource_and_registration() {
+        let _main_hub = MAIN_HUB.lock().unwrap_or_else(PoisonError::into_inner);
+        let (config, transport) = test_config();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .register_serialized_context::<Difficulty>("difficulty")
+            .add_plugin(SentryPlugin::new(config))
+            .insert_resource(Difficulty { level: 3 });
+        assert!(has_context(&mut app, &transport, "difficulty"));
+
+        app.world.remove_resource::<Difficulty>();
+        assert!(!has_context(&mut app, &transport, "difficulty"));
+
+        app.insert_resource(Difficulty { level: 2 });
+        assert!(has_context(&mut app, &transport, "difficulty"));
+
+        app.unregister_context::<Difficulty>();
+        assert!(!has_context(&mut app, &transport, "difficulty"));
+
+        app.register_serialized_context::<Difficulty>("difficulty");
+        assert!(has_context(&mut app, &transport, "difficulty"));
+    }
 }
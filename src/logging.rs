@@ -0,0 +1,206 @@
+use crate::panics::SystemTrackingLayer;
+use crate::performance::PerformanceLayer;
+use crate::SentryConfig;
+use bevy::app::{App, Plugin};
+use bevy::log::{Level, LogSettings};
+use bevy::utils::tracing::{Event, Subscriber};
+use sentry::integrations::tracing::EventFilter;
+use sentry::Hub;
+use tracing_log::LogTracer;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::{prelude::*, registry::Registry, EnvFilter};
+
+/// Replacement for Bevy's `LogPlugin` that additionally forwards log records to Sentry
+///
+/// Bevy's `LogPlugin` installs the global tracing subscriber and offers no way to add layers
+/// to it. This plugin sets up the same subscriber (configured through `LogSettings`) plus a
+/// Sentry layer, so it has to be used instead of `LogPlugin`:
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy::log::LogPlugin;
+/// # use bevy_sentry::{SentryConfig, SentryLogPlugin, SentryPlugin};
+/// let config = SentryConfig::from_options("https://examplePublicKey@o0.ingest.sentry.io/0");
+/// App::new()
+///     .add_plugins_with(DefaultPlugins, |group| group.disable::<LogPlugin>())
+///     .add_plugin(SentryLogPlugin::new(&config))
+///     .add_plugin(SentryPlugin::new(config))
+///     .run();
+/// ```
+///
+/// How records of each level are forwarded is configured with [`SentryConfig::with_log_level`].
+/// This plugin also records performance data if
+/// [performance monitoring](SentryConfig::with_performance_monitoring) is enabled and keeps
+/// track of the running systems to tag panic events with the system and stage that panicked.
+/// The plugin created by `SentryLogPlugin::default()` reads these settings from the
+/// `SentryConfig` resource instead, which needs to be inserted before the plugin is added.
+/// Without it, the default [`LogLevels`] are used and performance monitoring is disabled.
+///
+/// Log records are printed to stdout with `tracing_subscriber`'s `fmt` layer on every platform.
+/// The outputs that `LogPlugin` sets up for the browser console on wasm, for Android's log and
+/// for Bevy's `trace_chrome` and `trace_tracy` features are not available, so keep using
+/// `LogPlugin` on those targets.
+///
+/// # Panics
+///
+/// Like `LogPlugin`, this plugin sets up the global tracing subscriber and will panic if one is
+/// already set.
+#[derive(Default)]
+pub struct SentryLogPlugin {
+    settings: Option<LogPluginSettings>,
+}
+
+impl SentryLogPlugin {
+    /// Forward log records and record performance data as configured in the given config
+    ///
+    /// A `SentryConfig` resource is ignored.
+    pub fn new(config: &SentryConfig) -> Self {
+        SentryLogPlugin {
+            settings: Some(LogPluginSettings::new(config)),
+        }
+    }
+}
+
+/// The parts of the [`SentryConfig`] used by the [`SentryLogPlugin`]
+#[derive(Clone)]
+struct LogPluginSettings {
+    log_levels: LogLevels,
+    performance_monitoring: bool,
+    instrumented_stages: Vec<String>,
+}
+
+impl LogPluginSettings {
+    fn new(config: &SentryConfig) -> Self {
+        LogPluginSettings {
+            log_levels: config.log_levels.clone(),
+            performance_monitoring: config.performance_monitoring,
+            instrumented_stages: config.instrumented_stages.clone(),
+        }
+    }
+}
+
+impl Plugin for SentryLogPlugin {
+    fn build(&self, app: &mut App) {
+        let default_filter = {
+            let settings = app.world.get_resource_or_insert_with(LogSettings::default);
+            format!("{},{}", settings.level, settings.filter)
+        };
+        let settings = match &self.settings {
+            Some(settings) => Some(settings.clone()),
+            None => app
+                .world
+                .get_resource::<SentryConfig>()
+                .map(LogPluginSettings::new),
+        };
+        let (log_levels, performance_layer) = settings
+            .map(|settings| {
+                let performance_layer = if settings.performance_monitoring {
+                    Some(PerformanceLayer::new(settings.instrumented_stages))
+                } else {
+                    None
+                };
+                (settings.log_levels, performance_layer)
+            })
+            .unwrap_or_default();
+
+        LogTracer::init().unwrap();
+        let filter_layer = EnvFilter::try_from_default_env()
+            .or_else(|_| EnvFilter::try_new(&default_filter))
+            .unwrap();
+        // Spans are handled by the performance layer
+        let sentry_layer = sentry::integrations::tracing::layer()
+            .event_filter(move |metadata| log_levels.get(*metadata.level()).into())
+            .span_filter(|_| false);
+        let subscriber = Registry::default()
+            .with(filter_layer)
+            .with(tracing_subscriber::fmt::Layer::default())
+            .with(MainHubLayer(sentry_layer))
+            .with(SystemTrackingLayer)
+            .with(performance_layer);
+
+        bevy::utils::tracing::subscriber::set_global_default(subscriber)
+            .expect("Could not set global default tracing subscriber. If you've already set up a tracing subscriber, please disable LogPlugin from Bevy's DefaultPlugins");
+    }
+}
+
+/// Forwards log records to Sentry's main hub, regardless of the thread they are recorded on
+struct MainHubLayer<L>(L);
+
+impl<S, L> Layer<S> for MainHubLayer<L>
+where
+    S: Subscriber,
+    L: Layer<S>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        Hub::run(Hub::main(), || self.0.on_event(event, ctx));
+    }
+}
+
+/// How a log record is forwarded to Sentry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogForwarding {
+    /// The record is not sent to Sentry
+    Ignore,
+    /// The record is attached as breadcrumb to following Sentry events
+    Breadcrumb,
+    /// The record is captured as Sentry event
+    Event,
+}
+
+impl From<LogForwarding> for EventFilter {
+    fn from(forwarding: LogForwarding) -> Self {
+        match forwarding {
+            LogForwarding::Ignore => EventFilter::Ignore,
+            LogForwarding::Breadcrumb => EventFilter::Breadcrumb,
+            LogForwarding::Event => EventFilter::Event,
+        }
+    }
+}
+
+/// Mapping of log levels to the way records of that level are forwarded to Sentry
+///
+/// By default, `error` records become events, `warn` and `info` records become breadcrumbs
+/// and `debug` and `trace` records are ignored.
+#[derive(Clone, Debug)]
+pub struct LogLevels {
+    error: LogForwarding,
+    warn: LogForwarding,
+    info: LogForwarding,
+    debug: LogForwarding,
+    trace: LogForwarding,
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        LogLevels {
+            error: LogForwarding::Event,
+            warn: LogForwarding::Breadcrumb,
+            info: LogForwarding::Breadcrumb,
+            debug: LogForwarding::Ignore,
+            trace: LogForwarding::Ignore,
+        }
+    }
+}
+
+impl LogLevels {
+    /// Get the forwarding for records of the given level
+    pub fn get(&self, level: Level) -> LogForwarding {
+        match level {
+            Level::ERROR => self.error,
+            Level::WARN => self.warn,
+            Level::INFO => self.info,
+            Level::DEBUG => self.debug,
+            _ => self.trace,
+        }
+    }
+
+    /// Set the forwarding for records of the given level
+    pub fn set(&mut self, level: Level, forwarding: LogForwarding) {
+        match level {
+            Level::ERROR => self.error = forwarding,
+            Level::WARN => self.warn = forwarding,
+            Level::INFO => self.info = forwarding,
+            Level::DEBUG => self.debug = forwarding,
+            _ => self.trace = forwarding,
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use crate::http::{EnvelopeSender, SendError};
+use sentry::types::Uuid;
+use sentry::{ClientOptions, Envelope, Transport, TransportFactory};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_FILE_EXTENSION: &str = "envelope";
+/// Maximum number of envelopes waiting to be sent
+pub(crate) const QUEUE_SIZE: usize = 30;
+
+/// Configuration of the offline cache for events that could not be sent
+#[derive(Clone)]
+pub(crate) struct OfflineCacheSettings {
+    pub(crate) directory: PathBuf,
+    pub(crate) max_size: u64,
+    pub(crate) max_age: Duration,
+    /// Held while cached envelopes are replayed
+    ///
+    /// Every client created from the same options gets its own transport and cache, e.g. when
+    /// settings are reloaded. Sharing this lock makes sure only one of them replays at a time, so
+    /// no envelope is sent twice.
+    pub(crate) replay_lock: Arc<Mutex<()>>,
+}
+
+impl OfflineCacheSettings {
+    pub(crate) fn new(directory: PathBuf) -> Self {
+        OfflineCacheSettings {
+            directory,
+            max_size: 10 * 1024 * 1024,
+            max_age: Duration::from_secs(30 * 24 * 60 * 60),
+            replay_lock: Arc::default(),
+        }
+    }
+}
+
+/// Send events through a transport that caches them on disk if Sentry can not be reached
+pub(crate) fn setup_offline_cache(options: &mut ClientOptions, settings: OfflineCacheSettings) {
+    options.transport = Some(Arc::new(OfflineCacheTransportFactory { settings }));
+}
+
+struct OfflineCacheTransportFactory {
+    settings: OfflineCacheSettings,
+}
+
+impl TransportFactory for OfflineCacheTransportFactory {
+    fn create_transport(&self, options: &ClientOptions) -> Arc<dyn Transport> {
+        let sender = EnvelopeSender::new(options);
+        Arc::new(OfflineCacheTransport::new(
+            sender,
+            EnvelopeCache::new(self.settings.clone()),
+        ))
+    }
+}
+
+enum Task {
+    Send(Envelope),
+    Flush(SyncSender<()>),
+    Shutdown,
+}
+
+/// HTTP transport persisting envelopes that could not be sent
+///
+/// Envelopes are sent on a background thread. Cached envelopes are replayed when the transport
+/// is created and whenever sending succeeds again.
+struct OfflineCacheTransport {
+    tasks: SyncSender<Task>,
+}
+
+impl OfflineCacheTransport {
+    fn new(sender: Option<EnvelopeSender>, cache: EnvelopeCache) -> Self {
+        let (tasks, receiver) = sync_channel(QUEUE_SIZE);
+        let _ = thread::Builder::new()
+            .name("bevy_sentry-offline-cache".to_owned())
+            .spawn(move || send_envelopes(sender, cache, receiver));
+
+        OfflineCacheTransport { tasks }
+    }
+}
+
+impl Transport for OfflineCacheTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        // Drop envelopes if the queue is full instead of blocking the game
+        let _ = self.tasks.try_send(Task::Send(envelope));
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        let (done, flushed) = sync_channel(1);
+        // Blocking on a full queue would ignore the timeout
+        if self.tasks.try_send(Task::Flush(done)).is_err() {
+            return false;
+        }
+        flushed.recv_timeout(timeout).is_ok()
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        let flushed = self.flush(timeout);
+        // The thread also stops once the transport is dropped
+        let _ = self.tasks.try_send(Task::Shutdown);
+        flushed
+    }
+}
+
+fn send_envelopes(sender: Option<EnvelopeSender>, mut cache: EnvelopeCache, tasks: Receiver<Task>) {
+    if let Some(sender) = &sender {
+        cache.replay(sender);
+    }
+
+    for task in tasks {
+        match task {
+            Task::Send(envelope) => {
+                if let Some(body) = EnvelopeSender::serialize(&envelope) {
+                    cache.send(sender.as_ref(), &body);
+                }
+            }
+            Task::Flush(done) => {
+                let _ = done.send(());
+            }
+            Task::Shutdown => break,
+        }
+    }
+}
+
+/// Directory of serialized envelopes, bounded by size and age
+pub(crate) struct EnvelopeCache {
+    settings: OfflineCacheSettings,
+    /// Whether the cache may contain envelopes
+    pending: bool,
+}
+
+struct CachedEnvelope {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+impl EnvelopeCache {
+    pub(crate) fn new(settings: OfflineCacheSettings) -> Self {
+        EnvelopeCache {
+            settings,
+            pending: true,
+        }
+    }
+
+    /// Send a serialized envelope, caching it if Sentry is unavailable
+    ///
+    /// Cached envelopes are replayed once sending succeeds. Envelopes are dropped while Sentry
+    /// rate limits them, caching them would defeat the rate limit.
+    pub(crate) fn send(&mut self, sender: Option<&EnvelopeSender>, body: &[u8]) {
+        match sender.map(|sender| (sender, sender.send(body))) {
+            Some((sender, Ok(()))) if self.pending => self.replay(sender),
+            Some((_, Ok(())))
+            | Some((_, Err(SendError::Rejected)))
+            | Some((_, Err(SendError::RateLimited))) => {}
+            Some((_, Err(SendError::Unavailable))) | None => self.store(body),
+        }
+    }
+
+    fn store(&mut self, body: &[u8]) {
+        if fs::create_dir_all(&self.settings.directory).is_err() {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let file = self.settings.directory.join(format!(
+            "{:020}-{}.{}",
+            timestamp,
+            Uuid::new_v4(),
+            CACHE_FILE_EXTENSION
+        ));
+        if fs::write(file, body).is_ok() {
+            self.pending = true;
+        }
+        self.prune();
+    }
+
+    /// Send cached envelopes, oldest first, until Sentry becomes unavailable or rate limits them
+    ///
+    /// Nothing is sent while another cache of the same directory replays its envelopes.
+    pub(crate) fn replay(&mut self, sender: &EnvelopeSender) {
+        let replay_lock = self.settings.replay_lock.clone();
+        let _replaying = match replay_lock.try_lock() {
+            Ok(replaying) => replaying,
+            Err(_) => return,
+        };
+        for envelope in self.prune() {
+            if let Ok(body) = fs::read(&envelope.path) {
+                if let Err(SendError::Unavailable | SendError::RateLimited) = sender.send(&body) {
+                    return;
+                }
+            }
+            let _ = fs::remove_file(&envelope.path);
+        }
+        self.pending = false;
+    }
+
+    /// Remove envelopes exceeding the age or size limit and return the remaining ones
+    fn prune(&self) -> Vec<CachedEnvelope> {
+        let mut envelopes = self.cached_envelopes();
+        let now = SystemTime::now();
+        envelopes.retain(|envelope| {
+            let expired = now
+                .duration_since(envelope.modified)
+                .map_or(false, |age| age > self.settings.max_age);
+            if expired {
+                let _ = fs::remove_file(&envelope.path);
+            }
+            !expired
+        });
+
+        // Keep the newest envelopes within the size limit
+        let mut size = 0;
+        let mut kept = Vec::new();
+        for envelope in envelopes.into_iter().rev() {
+            size += envelope.size;
+            if size > self.settings.max_size {
+                let _ = fs::remove_file(&envelope.path);
+            } else {
+                kept.push(envelope);
+            }
+        }
+        kept.reverse();
+        kept
+    }
+
+    /// Cached envelopes, oldest first
+    fn cached_envelopes(&self) -> Vec<CachedEnvelope> {
+        let entries = match fs::read_dir(&self.settings.directory) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+        let mut envelopes: Vec<_> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != CACHE_FILE_EXTENSION {
+                    return None;
+                }
+                let metadata = fs::metadata(&path).ok()?;
+                Some(CachedEnvelope {
+                    modified: metadata.modified().ok()?,
+                    size: metadata.len(),
+                    path,
+                })
+            })
+            .collect();
+        // File names start with the time they were cached
+        envelopes.sort_by(|a, b| a.path.cmp(&b.path));
+        envelopes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sentry::IntoDsn;
+    use std::env;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::Mutex;
+
+    /// Local HTTP server answering every request with the same response
+    struct MockServer {
+        address: SocketAddr,
+        bodies: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl MockServer {
+        fn start(status: u16, headers: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            let bodies = Arc::new(Mutex::new(vec![]));
+            let received = bodies.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => break,
+                    };
+                    if let Some(body) = read_body(&stream) {
+                        received.lock().unwrap().push(body);
+                    }
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} Mock\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+                        status, headers
+                    );
+                }
+            });
+
+            MockServer { address, bodies }
+        }
+
+        fn sender(&self) -> EnvelopeSender {
+            let options = ClientOptions {
+                dsn: format!("http://public@{}/1", self.address)
+                    .into_dsn()
+                    .unwrap(),
+                ..Default::default()
+            };
+            EnvelopeSender::new(&options).unwrap()
+        }
+
+        fn bodies(&self) -> Vec<Vec<u8>> {
+            self.bodies.lock().unwrap().clone()
+        }
+    }
+
+    fn read_body(stream: &TcpStream) -> Option<Vec<u8>> {
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().ok()?;
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).ok()?;
+        Some(body)
+    }
+
+    /// Settings for an empty cache directory
+    fn settings(max_size: u64, max_age: Duration) -> OfflineCacheSettings {
+        OfflineCacheSettings {
+            max_size,
+            max_age,
+            ..OfflineCacheSettings::new(
+                env::temp_dir().join(format!("bevy_sentry-test-{}", Uuid::new_v4())),
+            )
+        }
+    }
+
+    fn default_settings() -> OfflineCacheSettings {
+        let defaults = OfflineCacheSettings::new(PathBuf::new());
+        settings(defaults.max_size, defaults.max_age)
+    }
+
+    fn cached_bodies(settings: &OfflineCacheSettings) -> Vec<Vec<u8>> {
+        EnvelopeCache::new(settings.clone())
+            .cached_envelopes()
+            .iter()
+            .map(|envelope| fs::read(&envelope.path).unwrap())
+            .collect()
+    }
+
+    /// Write an envelope file that sorts by the given number
+    fn write_envelope(settings: &OfflineCacheSettings, number: u64, size: usize) -> PathBuf {
+        fs::create_dir_all(&settings.directory).unwrap();
+        let path = settings
+            .directory
+            .join(format!("{:020}-test.{}", number, CACHE_FILE_EXTENSION));
+        fs::write(&path, vec![b'x'; size]).unwrap();
+        path
+    }
+
+    #[test]
+    fn stores_envelopes_if_sentry_is_unavailable() {
+        let server = MockServer::start(503, "");
+        let settings = default_settings();
+        let mut cache = EnvelopeCache::new(settings.clone());
+
+        cache.send(Some(&server.sender()), b"unavailable");
+        cache.send(None, b"no dsn");
+
+        assert_eq!(server.bodies(), vec![b"unavailable".to_vec()]);
+        let mut cached = cached_bodies(&settings);
+        cached.sort();
+        assert_eq!(cached, vec![b"no dsn".to_vec(), b"unavailable".to_vec()]);
+        fs::remove_dir_all(&settings.directory).unwrap();
+    }
+
+    #[test]
+    fn drops_rate_limited_envelopes() {
+        let server = MockServer::start(429, "Retry-After: 60\r\n");
+        let settings = default_settings();
+        let mut cache = EnvelopeCache::new(settings.clone());
+        let sender = server.sender();
+
+        cache.send(Some(&sender), b"rate limited");
+        cache.send(Some(&sender), b"within retry after");
+
+        // The second envelope is dropped without a request
+        assert_eq!(server.bodies(), vec![b"rate limited".to_vec()]);
+        assert!(cached_bodies(&settings).is_empty());
+    }
+
+    #[test]
+    fn replays_cached_envelopes_on_next_start() {
+        let settings = default_settings();
+        EnvelopeCache::new(settings.clone()).send(None, b"first");
+        EnvelopeCache::new(settings.clone()).send(None, b"second");
+
+        let server = MockServer::start(200, "");
+        let transport =
+            OfflineCacheTransport::new(Some(server.sender()), EnvelopeCache::new(settings.clone()));
+        assert!(transport.flush(Duration::from_secs(5)));
+
+        let mut replayed = server.bodies();
+        replayed.sort();
+        assert_eq!(replayed, vec![b"first".to_vec(), b"second".to_vec()]);
+        assert!(cached_bodies(&settings).is_empty());
+        fs::remove_dir_all(&settings.directory).unwrap();
+    }
+
+    #[test]
+    fn prunes_oldest_envelopes_exceeding_the_size_limit() {
+        let settings = settings(25, Duration::from_secs(60));
+        let oldest = write_envelope(&settings, 1, 10);
+        let older = write_envelope(&settings, 2, 10);
+        let newest = write_envelope(&settings, 3, 10);
+
+        let kept: Vec<_> = EnvelopeCache::new(settings.clone())
+            .prune()
+            .into_iter()
+            .map(|envelope| envelope.path)
+            .collect();
+
+        assert_eq!(kept, vec![older, newest]);
+        assert!(!oldest.exists());
+        fs::remove_dir_all(&settings.directory).unwrap();
+    }
+
+    #[test]
+    fn prunes_expired_envelopes() {
+        let settings = settings(1024, Duration::ZERO);
+        let expired = write_envelope(&settings, 1, 10);
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(EnvelopeCache::new(settings.clone()).prune().is_empty());
+        assert!(!expired.exists());
+        fs::remove_dir_all(&settings.directory).unwrap();
+    }
+}
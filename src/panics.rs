@@ -0,0 +1,177 @@
+use crate::errors::SYSTEM_TAG;
+use crate::performance::{NameVisitor, EXCLUSIVE_SYSTEM_SPAN, STAGE_SPAN, SYSTEM_SPAN};
+use bevy::utils::tracing::span::{Attributes, Id};
+use bevy::utils::tracing::Subscriber;
+use sentry::protocol::Event;
+use sentry::{ClientOptions, Hub};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::panic;
+use std::sync::Arc;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Tag holding the name of the stage an event originates from
+const STAGE_TAG: &str = "bevy.stage";
+/// Tag holding the name of the task an event originates from
+const TASK_TAG: &str = "bevy.task";
+
+/// Mechanism of events captured by Sentry's panic integration
+const PANIC_MECHANISM: &str = "panic";
+
+thread_local! {
+    /// Systems, stages and tasks currently running on this thread, innermost last
+    static RUNNING: RefCell<Vec<Running>> = RefCell::new(Vec::new());
+}
+
+/// A system, stage or task running on a thread
+#[derive(Clone, Default)]
+pub(crate) struct Running {
+    pub(crate) system: Option<String>,
+    pub(crate) stage: Option<String>,
+    pub(crate) task: Option<String>,
+}
+
+impl Running {
+    /// The innermost system, stage or task running on this thread
+    pub(crate) fn current() -> Option<Running> {
+        RUNNING.with(|stack| stack.borrow().last().cloned())
+    }
+
+    /// Mark this as running on the current thread while calling `f`
+    pub(crate) fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        RUNNING.with(|stack| stack.borrow_mut().push(self.clone()));
+        let _guard = RunningGuard;
+        f()
+    }
+
+    /// Tags identifying where an event originates from
+    pub(crate) fn tags(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            (STAGE_TAG, &self.stage),
+            (SYSTEM_TAG, &self.system),
+            (TASK_TAG, &self.task),
+        ]
+        .into_iter()
+        .filter_map(|(tag, value)| Some((tag, value.as_deref()?)))
+    }
+}
+
+/// Removes the innermost entry of [`RUNNING`], even if the running code panics
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// Tracing layer keeping track of the system and stage running on each thread
+///
+/// Bevy only emits stage and system spans with its `trace` feature enabled.
+pub(crate) struct SystemTrackingLayer;
+
+impl<S> Layer<S> for SystemTrackingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = NameVisitor::default();
+        attributes.record(&mut visitor);
+        let (span, name) = match (ctx.span(id), visitor.0) {
+            (Some(span), Some(name)) => (span, name),
+            _ => return,
+        };
+        let running = match attributes.metadata().name() {
+            STAGE_SPAN => Running {
+                stage: Some(name),
+                ..Default::default()
+            },
+            SYSTEM_SPAN | EXCLUSIVE_SYSTEM_SPAN => {
+                // Systems of the parallel executor run on other threads than their stage
+                let stage = span.scope().skip(1).find_map(|ancestor| {
+                    let extensions = ancestor.extensions();
+                    let stage = extensions
+                        .get::<Running>()
+                        .and_then(|running| running.stage.clone());
+                    stage
+                });
+                Running {
+                    system: Some(name),
+                    stage,
+                    task: None,
+                }
+            }
+            _ => return,
+        };
+        span.extensions_mut().insert(running);
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(running) = span.extensions().get::<Running>() {
+                RUNNING.with(|stack| stack.borrow_mut().push(running.clone()));
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if span.extensions().get::<Running>().is_some() {
+                RUNNING.with(|stack| stack.borrow_mut().pop());
+            }
+        }
+    }
+}
+
+/// Tag panic events with the system, stage or task that panicked
+///
+/// Panics inside systems are grouped by system instead of by their location, which is
+/// usually somewhere in Bevy's scheduler. Panics inside tasks are grouped by task.
+pub(crate) fn tag_system_panics(options: &mut ClientOptions) {
+    let before_send = options.before_send.take();
+    options.before_send = Some(Arc::new(move |event| {
+        let event = tag_panic(event);
+        match &before_send {
+            Some(before_send) => before_send(event),
+            None => Some(event),
+        }
+    }));
+}
+
+/// Capture panics through the main hub
+///
+/// Systems run on the threads of Bevy's task pools. Their hubs are copies of the main hub that
+/// miss later changes to the scope, e.g. updated contexts. Panics of tasks spawned with
+/// [`spawn_with_sentry`](crate::spawn_with_sentry) are captured through the hub bound to the
+/// task instead. This needs to be called after Sentry installed its panic hook.
+pub(crate) fn capture_panics_with_main_hub() {
+    let next = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let in_task = Running::current().map_or(false, |running| running.task.is_some());
+        let hub = if in_task { Hub::current() } else { Hub::main() };
+        Hub::run(hub, || next(info))
+    }));
+}
+
+fn tag_panic(mut event: Event<'static>) -> Event<'static> {
+    let is_panic = event.exception.values.iter().any(|exception| {
+        matches!(&exception.mechanism, Some(mechanism) if mechanism.ty == PANIC_MECHANISM)
+    });
+    let running = match Running::current() {
+        Some(running) if is_panic => running,
+        _ => return event,
+    };
+
+    for (tag, value) in running.tags() {
+        event.tags.insert(tag.to_owned(), value.to_owned());
+    }
+    if let Some(group) = running.task.as_ref().or(running.system.as_ref()) {
+        event.fingerprint = Cow::Owned(vec![
+            Cow::Borrowed(PANIC_MECHANISM),
+            Cow::Owned(group.clone()),
+        ]);
+    }
+
+    event
+}
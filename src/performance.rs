@@ -0,0 +1,129 @@
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::span::{Attributes, Id};
+use bevy::utils::tracing::Subscriber;
+use sentry::{Hub, TransactionContext, TransactionOrSpan};
+use std::fmt::Debug;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+const FRAME_SPAN: &str = "frame";
+pub(crate) const STAGE_SPAN: &str = "stage";
+pub(crate) const SYSTEM_SPAN: &str = "system";
+/// Span of exclusive systems, which Bevy runs outside of the parallel executor
+pub(crate) const EXCLUSIVE_SYSTEM_SPAN: &str = "exclusive_system";
+const NAME_FIELD: &str = "name";
+
+const FRAME_OPERATION: &str = "bevy.frame";
+const STAGE_OPERATION: &str = "bevy.stage";
+const SYSTEM_OPERATION: &str = "bevy.system";
+
+/// Tracing layer turning Bevy's frame, stage and system spans into Sentry transactions and spans
+///
+/// Bevy only emits these spans with its `trace` feature enabled.
+pub(crate) struct PerformanceLayer {
+    stages: Vec<String>,
+}
+
+impl PerformanceLayer {
+    /// Without any stages, every frame becomes a transaction
+    pub(crate) fn new(stages: Vec<String>) -> Self {
+        PerformanceLayer { stages }
+    }
+}
+
+struct InstrumentedSpan {
+    operation: &'static str,
+    name: String,
+    started: bool,
+    sentry: Option<TransactionOrSpan>,
+}
+
+impl<S> Layer<S> for PerformanceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = NameVisitor::default();
+        attributes.record(&mut visitor);
+        let (operation, name) = match (attributes.metadata().name(), visitor.0) {
+            (FRAME_SPAN, _) if self.stages.is_empty() => (FRAME_OPERATION, FRAME_SPAN.to_owned()),
+            (STAGE_SPAN, Some(name)) if self.stages.contains(&name) => (STAGE_OPERATION, name),
+            (SYSTEM_SPAN | EXCLUSIVE_SYSTEM_SPAN, Some(name)) => (SYSTEM_OPERATION, name),
+            _ => return,
+        };
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(InstrumentedSpan {
+                operation,
+                name,
+                started: false,
+                sentry: None,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let (operation, name) = match span.extensions().get::<InstrumentedSpan>() {
+            Some(instrumented) if !instrumented.started => {
+                (instrumented.operation, instrumented.name.clone())
+            }
+            _ => return,
+        };
+
+        let sentry = if operation == SYSTEM_OPERATION {
+            // Systems only become spans if one of their ancestors is an instrumented transaction
+            span.scope().skip(1).find_map(|ancestor| {
+                let extensions = ancestor.extensions();
+                let parent = extensions
+                    .get::<InstrumentedSpan>()
+                    .and_then(|instrumented| instrumented.sentry.clone());
+                parent.map(|parent| parent.start_child(operation, &name).into())
+            })
+        } else {
+            // Stages and frames may run on any thread, so transactions are started on the main hub
+            Some(
+                Hub::main()
+                    .start_transaction(TransactionContext::new(&name, operation))
+                    .into(),
+            )
+        };
+
+        if let Some(instrumented) = span.extensions_mut().get_mut::<InstrumentedSpan>() {
+            instrumented.started = true;
+            instrumented.sentry = sentry;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let instrumented = span.extensions_mut().remove::<InstrumentedSpan>();
+        if let Some(sentry) = instrumented.and_then(|instrumented| instrumented.sentry) {
+            sentry.finish();
+        }
+    }
+}
+
+/// Extracts the `name` field Bevy puts on stage and system spans
+#[derive(Default)]
+pub(crate) struct NameVisitor(pub(crate) Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == NAME_FIELD {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == NAME_FIELD {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
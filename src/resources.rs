@@ -0,0 +1,116 @@
+use crate::add_snapshot_system;
+use crate::tracking::reflect_to_value;
+use bevy::app::App;
+use bevy::ecs::system::Resource;
+use bevy::log::warn;
+use bevy::prelude::{Local, Res};
+use bevy::reflect::{Reflect, TypeRegistryArc};
+use sentry::protocol::{Context, Event, Map};
+use sentry::Hub;
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Key of the context holding the snapshots of resources
+pub(crate) const RESOURCE_SNAPSHOTS_CONTEXT: &str = "bevy.resources";
+
+/// Adds the snapshot system for one resource type to the app
+pub(crate) type ResourceSnapshotRegistration = fn(&mut App);
+
+#[derive(Clone, Default)]
+struct ResourceSnapshots(Arc<Mutex<BTreeMap<&'static str, Box<dyn Reflect>>>>);
+
+pub(crate) fn setup_resource_snapshots(
+    app: &mut App,
+    hub: &Hub,
+    registrations: &[ResourceSnapshotRegistration],
+) {
+    if registrations.is_empty() {
+        return;
+    }
+    let registry = if let Some(registry) = app.world.get_resource::<TypeRegistryArc>() {
+        registry.clone()
+    } else {
+        warn!(
+            "Attaching resource snapshots to Sentry events requires the `TypeRegistryArc` resource"
+        );
+        return;
+    };
+
+    let snapshots = ResourceSnapshots::default();
+    let processor_snapshots = snapshots.clone();
+    hub.configure_scope(|scope| {
+        scope.add_event_processor(move |event| {
+            Some(attach_resource_snapshots(
+                event,
+                &processor_snapshots,
+                &registry,
+            ))
+        });
+    });
+    app.insert_resource(snapshots);
+    for register in registrations {
+        register(app);
+    }
+}
+
+pub(crate) fn register_resource_snapshot<T: Resource + Reflect>(app: &mut App) {
+    add_snapshot_system(app, snapshot_resource::<T>);
+}
+
+/// Clone the reflected value of the resource whenever it changes
+///
+/// Serialization is deferred until an event is actually sent. The clone can not be deferred as
+/// well, because event processors have no access to the `World`.
+fn snapshot_resource<T: Resource + Reflect>(
+    resource: Option<Res<T>>,
+    snapshots: Res<ResourceSnapshots>,
+    mut stored: Local<bool>,
+) {
+    match resource {
+        Some(resource) if resource.is_changed() || !*stored => {
+            if let Ok(mut snapshots) = snapshots.0.lock() {
+                snapshots.insert(type_name::<T>(), resource.clone_value());
+                *stored = true;
+            }
+        }
+        None if *stored => {
+            if let Ok(mut snapshots) = snapshots.0.lock() {
+                snapshots.remove(type_name::<T>());
+                *stored = false;
+            }
+        }
+        _ => (),
+    }
+}
+
+fn attach_resource_snapshots(
+    mut event: Event<'static>,
+    snapshots: &ResourceSnapshots,
+    registry: &TypeRegistryArc,
+) -> Event<'static> {
+    let snapshots = match snapshots.0.lock() {
+        Ok(snapshots) => snapshots,
+        Err(_) => return event,
+    };
+    if snapshots.is_empty() {
+        return event;
+    }
+    let registry = registry.read();
+
+    let context: Map<_, _> = snapshots
+        .iter()
+        .map(|(name, resource)| {
+            (
+                (*name).to_owned(),
+                reflect_to_value(resource.as_ref(), &registry),
+            )
+        })
+        .collect();
+    event.contexts.insert(
+        RESOURCE_SNAPSHOTS_CONTEXT.to_owned(),
+        Context::Other(context),
+    );
+
+    event
+}
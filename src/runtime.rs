@@ -0,0 +1,152 @@
+use crate::add_snapshot_system;
+use bevy::app::App;
+use bevy::core::Time;
+use bevy::prelude::{Local, Res};
+use bevy::window::Windows;
+use sentry::protocol::{Context, Event, Map, Value};
+use sentry::Hub;
+use std::sync::{Arc, Mutex};
+
+/// Key of the context holding information about the Bevy runtime
+pub(crate) const BEVY_CONTEXT: &str = "bevy";
+
+/// Bevy version `bevy_sentry` is built against, checked against `Cargo.toml` by a test
+const BEVY_VERSION: &str = "0.6";
+const BEVY_SENTRY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Configuration of the automatic `bevy` context
+#[derive(Clone)]
+pub(crate) struct RuntimeContextSettings {
+    pub(crate) enabled: bool,
+    pub(crate) window: bool,
+}
+
+impl Default for RuntimeContextSettings {
+    fn default() -> Self {
+        RuntimeContextSettings {
+            enabled: true,
+            window: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RuntimeState {
+    frame_count: u64,
+    /// Only known with the `Time` of Bevy's `CorePlugin`
+    seconds_since_startup: Option<f64>,
+    window: Option<WindowState>,
+}
+
+struct WindowState {
+    width: f32,
+    height: f32,
+    scale_factor: f64,
+    mode: String,
+}
+
+struct RuntimeContext {
+    window: bool,
+    state: Arc<Mutex<RuntimeState>>,
+}
+
+pub(crate) fn setup_runtime_context(app: &mut App, hub: &Hub, settings: RuntimeContextSettings) {
+    if !settings.enabled {
+        return;
+    }
+
+    let state = Arc::new(Mutex::new(RuntimeState::default()));
+    let processor_state = state.clone();
+    hub.configure_scope(|scope| {
+        scope.add_event_processor(move |event| {
+            Some(attach_runtime_context(event, &processor_state))
+        });
+    });
+    app.insert_resource(RuntimeContext {
+        window: settings.window,
+        state,
+    });
+    add_snapshot_system(app, update_runtime_state);
+}
+
+fn update_runtime_state(
+    context: Res<RuntimeContext>,
+    time: Option<Res<Time>>,
+    windows: Option<Res<Windows>>,
+    mut frame_count: Local<u64>,
+) {
+    *frame_count += 1;
+    let window = match windows {
+        Some(windows) if context.window => windows.get_primary().map(|window| WindowState {
+            width: window.width(),
+            height: window.height(),
+            scale_factor: window.scale_factor(),
+            mode: format!("{:?}", window.mode()),
+        }),
+        _ => None,
+    };
+
+    if let Ok(mut state) = context.state.lock() {
+        *state = RuntimeState {
+            frame_count: *frame_count,
+            seconds_since_startup: time.map(|time| time.seconds_since_startup()),
+            window,
+        };
+    }
+}
+
+fn attach_runtime_context(
+    mut event: Event<'static>,
+    state: &Mutex<RuntimeState>,
+) -> Event<'static> {
+    let state = match state.lock() {
+        Ok(state) => state,
+        Err(_) => return event,
+    };
+
+    let mut context = Map::new();
+    context.insert("bevy_version".to_owned(), BEVY_VERSION.into());
+    context.insert("bevy_sentry_version".to_owned(), BEVY_SENTRY_VERSION.into());
+    context.insert("frame_count".to_owned(), state.frame_count.into());
+    if let Some(seconds_since_startup) = state.seconds_since_startup {
+        context.insert(
+            "seconds_since_startup".to_owned(),
+            seconds_since_startup.into(),
+        );
+    }
+    if let Some(window) = &state.window {
+        let mut window_context = Map::new();
+        window_context.insert("width".to_owned(), window.width.into());
+        window_context.insert("height".to_owned(), window.height.into());
+        window_context.insert("scale_factor".to_owned(), window.scale_factor.into());
+        window_context.insert("mode".to_owned(), window.mode.clone().into());
+        context.insert(
+            "window".to_owned(),
+            Value::Object(window_context.into_iter().collect()),
+        );
+    }
+    event
+        .contexts
+        .insert(BEVY_CONTEXT.to_owned(), Context::Other(context));
+
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BEVY_VERSION;
+
+    #[test]
+    fn bevy_version_matches_dependency() {
+        let dependency = include_str!("../Cargo.toml")
+            .lines()
+            .find(|line| line.starts_with("bevy = "))
+            .expect("Cargo.toml has no Bevy dependency");
+        assert!(
+            dependency.contains(&format!("version = \"{}.", BEVY_VERSION)),
+            "BEVY_VERSION {} does not match the dependency `{}`",
+            BEVY_VERSION,
+            dependency
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use sentry::protocol::value::{from_value, to_value};
+use sentry::protocol::{EnvelopeItem, SessionStatus, SessionUpdate, Value};
+use sentry::transports::DefaultTransportFactory;
+use sentry::{Client, ClientOptions, Envelope, SessionMode, Transport, TransportFactory};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Prepare detection of sessions that ended abnormally
+///
+/// The last open session of the application is kept in a file. If the file still exists on the
+/// next start, the process died without exiting or crashing and the session is returned to be
+/// reported as abnormal. This only works for sessions in [`SessionMode::Application`].
+pub(crate) fn setup_session_tracking(
+    options: &mut ClientOptions,
+    file: Option<PathBuf>,
+) -> Option<SessionUpdate<'static>> {
+    if !options.auto_session_tracking || options.session_mode != SessionMode::Application {
+        return None;
+    }
+    let file = match (file, &options.release) {
+        (Some(file), _) => file,
+        (None, Some(release)) => default_session_file(release),
+        // Sessions require a release
+        (None, None) => return None,
+    };
+
+    let unfinished_session = fs::read_to_string(&file)
+        .ok()
+        .and_then(|session| Value::from_str(&session).ok())
+        .and_then(|session| from_value::<SessionUpdate<'static>>(session).ok());
+    let _ = fs::remove_file(&file);
+
+    let inner = options
+        .transport
+        .take()
+        .unwrap_or_else(|| Arc::new(DefaultTransportFactory));
+    options.transport = Some(Arc::new(SessionRecordingTransportFactory { inner, file }));
+
+    unfinished_session
+}
+
+/// Record the session started by `sentry::init` right away
+///
+/// Session updates are only sent periodically, so a process dying within the first minute would
+/// otherwise leave no file behind. Flushing the client sends the pending update through the
+/// recording transport.
+pub(crate) fn record_started_session(client: &Client) {
+    let options = client.options();
+    if options.auto_session_tracking && options.session_mode == SessionMode::Application {
+        client.flush(Some(Duration::ZERO));
+    }
+}
+
+/// Send the final update of a session that ended abnormally
+pub(crate) fn report_abnormal_session(client: &Client, mut session: SessionUpdate<'static>) {
+    session.init = false;
+    session.status = SessionStatus::Abnormal;
+    session.timestamp = Some(SystemTime::now());
+    let mut envelope = Envelope::new();
+    envelope.add_item(session);
+    client.send_envelope(envelope);
+}
+
+/// Session file in the temporary directory named after the release
+fn default_session_file(release: &str) -> PathBuf {
+    let release: String = release
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    std::env::temp_dir().join(format!("bevy_sentry_session_{}.json", release))
+}
+
+struct SessionRecordingTransportFactory {
+    inner: Arc<dyn TransportFactory>,
+    file: PathBuf,
+}
+
+impl TransportFactory for SessionRecordingTransportFactory {
+    fn create_transport(&self, options: &ClientOptions) -> Arc<dyn Transport> {
+        Arc::new(SessionRecordingTransport {
+            inner: self.inner.create_transport(options),
+            file: self.file.clone(),
+        })
+    }
+}
+
+/// Transport keeping the last sent update of an open session in a file
+struct SessionRecordingTransport {
+    inner: Arc<dyn Transport>,
+    file: PathBuf,
+}
+
+impl SessionRecordingTransport {
+    fn record(&self, session: &SessionUpdate) {
+        if session.status == SessionStatus::Ok {
+            if let Ok(session) = to_value(session) {
+                let _ = fs::write(&self.file, session.to_string());
+            }
+        } else {
+            let _ = fs::remove_file(&self.file);
+        }
+    }
+}
+
+impl Transport for SessionRecordingTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        for item in envelope.items() {
+            if let EnvelopeItem::SessionUpdate(session) = item {
+                self.record(session);
+            }
+        }
+        self.inner.send_envelope(envelope);
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        self.inner.flush(timeout)
+    }
+
+    fn shutdown(&self, timeout: Duration) -> bool {
+        self.inner.shutdown(timeout)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{Sentry, SentrySystem};
+use bevy::app::{App, CoreStage};
+use bevy::asset::{
+    AddAsset, AssetEvent, AssetLoader, AssetServer, Assets, BoxedFuture, Handle, LoadContext,
+    LoadedAsset,
+};
+use bevy::ecs::schedule::ParallelSystemDescriptorCoercion;
+use bevy::log::{error, info, warn};
+use bevy::prelude::{EventReader, Res};
+use bevy::reflect::TypeUuid;
+use sentry::{Client, ClientOptions, IntoDsn, Level};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Sentry settings loaded from a `*.sentry.ron` asset
+///
+/// All settings are optional and fall back to the [`SentryConfig`](crate::SentryConfig).
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// (
+///     dsn: "https://examplePublicKey@o0.ingest.sentry.io/0",
+///     environment: "production",
+///     sample_rate: 0.25,
+///     traces_sample_rate: 0.01,
+///     max_breadcrumbs: 50,
+///     min_level: "warning",
+///     integrations: ["contexts", "panic"],
+/// )
+/// ```
+#[derive(Debug, Default, Deserialize, TypeUuid)]
+#[uuid = "2de28bc4-acdd-4f2b-b0d6-ddac38625443"]
+#[serde(default, deny_unknown_fields)]
+pub struct SentrySettings {
+    /// Turn reporting to Sentry off or on
+    ///
+    /// `true` only turns reporting on again after the settings turned it off. Settings are not
+    /// loaded if Sentry is disabled by the [`SentryConfig`](crate::SentryConfig), e.g. with
+    /// `SENTRY_DISABLED` or `--sentry-disabled`, so they can not enable it.
+    pub enabled: Option<bool>,
+    /// DSN of the Sentry project
+    pub dsn: Option<String>,
+    /// Environment of the application, e.g. `production`
+    pub environment: Option<String>,
+    /// Release of the application
+    pub release: Option<String>,
+    /// Fraction of events that are sent (0.0 - 1.0)
+    pub sample_rate: Option<f32>,
+    /// Fraction of transactions that are sent (0.0 - 1.0)
+    pub traces_sample_rate: Option<f32>,
+    /// Maximum number of breadcrumbs attached to an event
+    pub max_breadcrumbs: Option<usize>,
+    /// Drop events below this level, e.g. `"warning"`
+    pub min_level: Option<Level>,
+    /// Names of the Sentry integrations to keep, e.g. `"contexts"` or `"panic"`
+    pub integrations: Option<Vec<String>>,
+}
+
+impl SentrySettings {
+    /// Client options with these settings applied on top of the given ones
+    fn apply(&self, options: &ClientOptions) -> ClientOptions {
+        let mut options = options.clone();
+        if let Some(dsn) = &self.dsn {
+            match dsn.as_str().into_dsn() {
+                Ok(dsn) => options.dsn = dsn,
+                Err(error) => warn!("Invalid Sentry DSN '{}': {}", dsn, error),
+            }
+        }
+        if let Some(environment) = &self.environment {
+            options.environment = Some(Cow::Owned(environment.clone()));
+        }
+        if let Some(release) = &self.release {
+            options.release = Some(Cow::Owned(release.clone()));
+        }
+        if let Some(sample_rate) = valid_sample_rate("sample_rate", self.sample_rate) {
+            options.sample_rate = sample_rate;
+        }
+        if let Some(sample_rate) = valid_sample_rate("traces_sample_rate", self.traces_sample_rate)
+        {
+            options.traces_sample_rate = sample_rate;
+        }
+        if let Some(max_breadcrumbs) = self.max_breadcrumbs {
+            options.max_breadcrumbs = max_breadcrumbs;
+        }
+        if let Some(min_level) = self.min_level {
+            let before_send = options.before_send.take();
+            options.before_send = Some(Arc::new(move |event| {
+                if event.level < min_level {
+                    return None;
+                }
+                match &before_send {
+                    Some(before_send) => before_send(event),
+                    None => Some(event),
+                }
+            }));
+        }
+        if let Some(integrations) = &self.integrations {
+            options
+                .integrations
+                .retain(|integration| integrations.iter().any(|name| name == integration.name()));
+        }
+
+        options
+    }
+}
+
+fn valid_sample_rate(setting: &str, sample_rate: Option<f32>) -> Option<f32> {
+    match sample_rate {
+        Some(sample_rate) if !(0. ..=1.).contains(&sample_rate) => {
+            warn!(
+                "Invalid sample rate {} for Sentry setting '{}', expected a value between 0 and 1",
+                sample_rate, setting
+            );
+            None
+        }
+        sample_rate => sample_rate,
+    }
+}
+
+/// Loads [`SentrySettings`] from files ending in `.sentry.ron`
+#[derive(Default)]
+struct SentrySettingsLoader;
+
+impl AssetLoader for SentrySettingsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let settings: SentrySettings = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(settings));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sentry.ron"]
+    }
+}
+
+/// The settings asset and the options it is applied to
+struct SentrySettingsAsset {
+    handle: Handle<SentrySettings>,
+    options: ClientOptions,
+}
+
+/// Load the settings asset and apply it whenever it is loaded or modified
+pub(crate) fn setup_settings_asset(app: &mut App, options: &ClientOptions, path: &str) {
+    // Adding the asset type panics without the asset server
+    if !app.world.contains_resource::<AssetServer>() {
+        error!("Loading Sentry settings requires Bevy's `AssetPlugin` before the `SentryPlugin`");
+        return;
+    }
+    app.add_asset::<SentrySettings>()
+        .init_asset_loader::<SentrySettingsLoader>();
+    let handle = app.world.get_resource::<AssetServer>().unwrap().load(path);
+
+    app.insert_resource(SentrySettingsAsset {
+        handle,
+        options: options.clone(),
+    })
+    .add_system_to_stage(
+        CoreStage::Last,
+        apply_settings.before(SentrySystem::Capture),
+    );
+}
+
+/// Bind a new client with the current settings to the main hub
+///
+/// Options of a Sentry client can not be changed, so every change creates a new client. The
+/// replaced client is flushed, but not closed: the first client is kept alive by the [`Sentry`]
+/// resource and a running session keeps reporting through the client it was started with.
+fn apply_settings(
+    sentry: Res<Sentry>,
+    asset: Res<SentrySettingsAsset>,
+    settings: Res<Assets<SentrySettings>>,
+    mut events: EventReader<AssetEvent<SentrySettings>>,
+) {
+    let changed = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == asset.handle,
+        AssetEvent::Removed { .. } => false,
+    });
+    if !changed {
+        return;
+    }
+    let settings = match settings.get(&asset.handle) {
+        Some(settings) => settings,
+        None => return,
+    };
+
+    let replaced = sentry.hub().client();
+    if settings.enabled == Some(false) {
+        info!("Sentry is disabled by its settings");
+        sentry.hub().bind_client(None);
+    } else {
+        let client = Client::from(settings.apply(&asset.options));
+        sentry.hub().bind_client(Some(Arc::new(client)));
+    }
+    if let Some(replaced) = replaced {
+        replaced.flush(None);
+    }
+}
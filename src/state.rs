@@ -0,0 +1,58 @@
+use crate::{short_type_name, Sentry};
+use bevy::ecs::schedule::{State, StateData};
+use bevy::prelude::{Local, Res};
+use sentry::protocol::{Breadcrumb, Map};
+use std::marker::PhantomData;
+
+const NAVIGATION_BREADCRUMB: &str = "navigation";
+const STATE_CATEGORY: &str = "bevy.state";
+
+/// Marks that transitions of the state `S` are tracked
+pub(crate) struct TrackedState<S>(PhantomData<S>);
+
+impl<S> Default for TrackedState<S> {
+    fn default() -> Self {
+        TrackedState(PhantomData::default())
+    }
+}
+
+/// Record state transitions of `S` as navigation breadcrumbs and tag events with the current state
+pub(crate) fn track_state<S: StateData>(
+    sentry: Option<Res<Sentry>>,
+    state: Option<Res<State<S>>>,
+    mut previous: Local<Option<S>>,
+) {
+    let (hub, current) = match (&sentry, &state) {
+        (Some(sentry), Some(state)) => (sentry.hub(), state.current()),
+        _ => return,
+    };
+    if previous.as_ref() == Some(current) {
+        return;
+    }
+
+    let mut data = Map::new();
+    if let Some(previous) = previous.as_ref() {
+        data.insert("from".to_owned(), format!("{:?}", previous).into());
+    }
+    data.insert("to".to_owned(), format!("{:?}", current).into());
+    hub.add_breadcrumb(Breadcrumb {
+        ty: NAVIGATION_BREADCRUMB.to_owned(),
+        category: Some(STATE_CATEGORY.to_owned()),
+        message: Some(match previous.as_ref() {
+            Some(previous) => format!("{:?} -> {:?}", previous, current),
+            None => format!("{:?}", current),
+        }),
+        data,
+        ..Default::default()
+    });
+    hub.configure_scope(|scope| {
+        scope.set_tag(&state_tag::<S>(), format!("{:?}", current));
+    });
+
+    *previous = Some(current.clone());
+}
+
+/// Tag name for the state type `S`, e.g. `state.AppState`
+fn state_tag<S>() -> String {
+    format!("state.{}", short_type_name::<S>())
+}
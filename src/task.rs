@@ -0,0 +1,74 @@
+use crate::panics::Running;
+use bevy::tasks::{Task, TaskPool};
+use sentry::{Hub, SentryFutureExt};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+/// Spawn a future on a Bevy task pool, e.g. the `AsyncComputeTaskPool` or `IoTaskPool`
+///
+/// The future runs with its own copy of Sentry's main hub, so events captured inside it carry
+/// the current contexts and breadcrumbs. Events and panics are tagged with the name of the task
+/// as `bevy.task` and with the system that spawned it as `bevy.system`. The spawning system is
+/// only known with the `trace` feature and the [`SentryLogPlugin`](crate::SentryLogPlugin).
+///
+/// A panic inside the future is captured on the hub of the task and returned as `Err` with the
+/// panic payload, instead of tearing down the thread of the task pool. Use
+/// [`std::panic::resume_unwind`] to propagate it.
+pub fn spawn_with_sentry<T, F>(
+    pool: &TaskPool,
+    name: impl Into<String>,
+    future: F,
+) -> Task<thread::Result<T>>
+where
+    T: Send + 'static,
+    F: Future<Output = T> + Send + 'static,
+{
+    let origin = Running::current().unwrap_or_default();
+    let running = Running {
+        task: Some(name.into()),
+        ..origin
+    };
+
+    let hub = Arc::new(Hub::new_from_top(Hub::main()));
+    hub.configure_scope(|scope| {
+        for (tag, value) in running.tags() {
+            scope.set_tag(tag, value);
+        }
+    });
+
+    pool.spawn(
+        SentryTask {
+            running,
+            future: Box::pin(future),
+        }
+        .bind_hub(hub),
+    )
+}
+
+/// Marks the task as running while it is polled, to tag panics inside it, and catches its panics
+struct SentryTask<F> {
+    running: Running,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for SentryTask<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let task = &mut *self;
+        let future = &mut task.future;
+        // The panic hook captures the panic before it is caught, while the task's hub is bound
+        let result = task
+            .running
+            .run(|| panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))));
+        match result {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
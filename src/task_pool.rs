@@ -0,0 +1,168 @@
+use crate::http::EnvelopeSender;
+use crate::offline::{EnvelopeCache, OfflineCacheSettings, QUEUE_SIZE};
+use bevy::tasks::TaskPool;
+use sentry::{ClientOptions, Envelope, Transport, TransportFactory};
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Send events on the given task pool
+///
+/// If the offline cache is enabled, events that can not be sent are cached as well.
+pub(crate) fn setup_task_pool_transport(
+    options: &mut ClientOptions,
+    pool: TaskPool,
+    offline_cache: Option<OfflineCacheSettings>,
+) {
+    options.transport = Some(Arc::new(TaskPoolTransportFactory {
+        pool,
+        offline_cache,
+    }));
+}
+
+struct TaskPoolTransportFactory {
+    pool: TaskPool,
+    offline_cache: Option<OfflineCacheSettings>,
+}
+
+impl TransportFactory for TaskPoolTransportFactory {
+    fn create_transport(&self, options: &ClientOptions) -> Arc<dyn Transport> {
+        let cache = self.offline_cache.clone().map(EnvelopeCache::new);
+        let transport = TaskPoolTransport {
+            pool: self.pool.clone(),
+            sender: EnvelopeSender::new(options),
+            queue: Arc::new(SendQueue {
+                state: Mutex::new(QueueState {
+                    envelopes: VecDeque::new(),
+                    draining: false,
+                    replay: cache.is_some(),
+                    cache,
+                }),
+                drained: Condvar::new(),
+            }),
+        };
+        transport.replay_cache();
+
+        Arc::new(transport)
+    }
+}
+
+/// HTTP transport sending envelopes in a task on a Bevy task pool
+///
+/// Envelopes are queued and sent one after another by a single task, so at most one thread of
+/// the pool waits for Sentry at a time.
+struct TaskPoolTransport {
+    pool: TaskPool,
+    sender: Option<EnvelopeSender>,
+    queue: Arc<SendQueue>,
+}
+
+/// Envelopes waiting to be sent
+struct SendQueue {
+    state: Mutex<QueueState>,
+    /// Notified when the queue was drained
+    drained: Condvar,
+}
+
+struct QueueState {
+    envelopes: VecDeque<Vec<u8>>,
+    /// Whether a task is sending the queued envelopes
+    draining: bool,
+    /// Whether cached envelopes still have to be replayed
+    replay: bool,
+    /// The offline cache, taken by the draining task while it sends
+    cache: Option<EnvelopeCache>,
+}
+
+impl TaskPoolTransport {
+    fn replay_cache(&self) {
+        if self.sender.is_none() {
+            return;
+        }
+        if let Ok(mut state) = self.queue.state.lock() {
+            if state.replay && !state.draining {
+                state.draining = true;
+                self.spawn_drain();
+            }
+        }
+    }
+
+    fn spawn_drain(&self) {
+        let sender = self.sender.clone();
+        let queue = self.queue.clone();
+        self.pool
+            .spawn(async move { drain(sender.as_ref(), &queue) })
+            .detach();
+    }
+}
+
+impl Transport for TaskPoolTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let body = match EnvelopeSender::serialize(&envelope) {
+            Some(body) => body,
+            None => return,
+        };
+        if let Ok(mut state) = self.queue.state.lock() {
+            // Drop envelopes if the queue is full instead of growing without bounds
+            if state.envelopes.len() >= QUEUE_SIZE {
+                return;
+            }
+            state.envelopes.push_back(body);
+            if !state.draining {
+                state.draining = true;
+                self.spawn_drain();
+            }
+        }
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        let state = match self.queue.state.lock() {
+            Ok(state) => state,
+            Err(_) => return false,
+        };
+        match self
+            .queue
+            .drained
+            .wait_timeout_while(state, timeout, |state| state.draining)
+        {
+            Ok((_, result)) => !result.timed_out(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Send queued envelopes until the queue is empty
+///
+/// The queue is only locked to take envelopes from it, never while sending.
+fn drain(sender: Option<&EnvelopeSender>, queue: &SendQueue) {
+    let (mut cache, replay) = match queue.state.lock() {
+        Ok(mut state) => (state.cache.take(), mem::take(&mut state.replay)),
+        Err(_) => return,
+    };
+    if let (true, Some(cache), Some(sender)) = (replay, &mut cache, sender) {
+        cache.replay(sender);
+    }
+
+    loop {
+        let body = match queue.state.lock() {
+            Ok(mut state) => match state.envelopes.pop_front() {
+                Some(body) => body,
+                None => {
+                    state.cache = cache;
+                    state.draining = false;
+                    queue.drained.notify_all();
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+        match (&mut cache, sender) {
+            (Some(cache), sender) => cache.send(sender, &body),
+            (None, Some(sender)) => {
+                let _ = sender.send(&body);
+            }
+            (None, None) => {}
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::SentrySystem;
+use bevy::app::{App, CoreStage};
+use bevy::core::{Name, Time};
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::ecs::schedule::ExclusiveSystemDescriptorCoercion;
+use bevy::log::warn;
+use bevy::prelude::{Component, Entity, IntoExclusiveSystem, With, World};
+use bevy::reflect::serde::ReflectSerializer;
+use bevy::reflect::{Reflect, TypeRegistry, TypeRegistryArc};
+use sentry::protocol::{value::to_value, Context, Event, Map, Value};
+use sentry::Hub;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Key of the context holding the snapshots of tracked entities
+pub(crate) const TRACKED_ENTITIES_CONTEXT: &str = "bevy.tracked_entities";
+
+/// Default for the maximum number of tracked entities attached to an event
+pub(crate) const DEFAULT_TRACKED_ENTITY_LIMIT: usize = 10;
+/// Default for the time between two snapshots of the tracked entities
+pub(crate) const DEFAULT_TRACKED_ENTITY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Marker component for entities whose state is attached to Sentry events
+///
+/// All reflected components of tracked entities are snapshotted at the end of a frame, once per
+/// [interval](crate::SentryConfig::with_tracked_entity_interval). The snapshots are only
+/// serialized when an event is sent. The number of entities included is bounded by
+/// [`SentryConfig::with_tracked_entity_limit`](crate::SentryConfig::with_tracked_entity_limit).
+#[derive(Component, Default)]
+pub struct SentryTracked;
+
+struct TrackedEntities {
+    limit: usize,
+    interval: Duration,
+    /// Seconds since startup of the last snapshot
+    last_snapshot: Option<f64>,
+    snapshots: Arc<Mutex<Vec<EntitySnapshot>>>,
+}
+
+struct EntitySnapshot {
+    entity: Entity,
+    name: Option<String>,
+    components: Vec<Box<dyn Reflect>>,
+}
+
+pub(crate) fn setup_entity_tracking(app: &mut App, hub: &Hub, limit: usize, interval: Duration) {
+    if limit == 0 {
+        return;
+    }
+    let registry = if let Some(registry) = app.world.get_resource::<TypeRegistryArc>() {
+        registry.clone()
+    } else {
+        warn!("Tracking entities for Sentry requires the `TypeRegistryArc` resource");
+        return;
+    };
+
+    let snapshots = Arc::new(Mutex::new(vec![]));
+    let processor_snapshots = snapshots.clone();
+    hub.configure_scope(|scope| {
+        scope.add_event_processor(move |event| {
+            Some(attach_tracked_entities(
+                event,
+                &processor_snapshots,
+                &registry,
+            ))
+        });
+    });
+    app.insert_resource(TrackedEntities {
+        limit,
+        interval,
+        last_snapshot: None,
+        snapshots,
+    })
+    .add_system_to_stage(
+        CoreStage::Last,
+        // Exclusive systems run before the parallel systems capturing events
+        snapshot_tracked_entities
+            .exclusive_system()
+            .label(SentrySystem::Snapshot),
+    );
+}
+
+/// Snapshot the tracked entities if the interval since the last snapshot passed
+///
+/// This is an exclusive system to reflect arbitrary components, so it is a sync point. Skipping
+/// most frames keeps the cost of cloning the components low.
+fn snapshot_tracked_entities(world: &mut World) {
+    let now = world
+        .get_resource::<Time>()
+        .map(|time| time.seconds_since_startup());
+    let (limit, snapshots) = {
+        let mut tracked = world.get_resource_mut::<TrackedEntities>().unwrap();
+        let due = match (tracked.last_snapshot, now) {
+            (Some(last), Some(now)) => now - last >= tracked.interval.as_secs_f64(),
+            _ => true,
+        };
+        if !due {
+            return;
+        }
+        tracked.last_snapshot = now;
+        (tracked.limit, tracked.snapshots.clone())
+    };
+    let registry = world.get_resource::<TypeRegistryArc>().unwrap().clone();
+    let registry = registry.read();
+    let mut query = world.query_filtered::<Entity, With<SentryTracked>>();
+    let world: &World = world;
+
+    let new_snapshots = query
+        .iter(world)
+        .take(limit)
+        .map(|entity| snapshot_entity(world, entity, &registry))
+        .collect();
+    if let Ok(mut snapshots) = snapshots.lock() {
+        *snapshots = new_snapshots;
+    }
+}
+
+fn snapshot_entity(world: &World, entity: Entity, registry: &TypeRegistry) -> EntitySnapshot {
+    let entity_ref = world.entity(entity);
+    let components = entity_ref
+        .archetype()
+        .components()
+        .filter_map(|component_id| world.components().get_info(component_id))
+        .filter_map(|info| info.type_id())
+        .filter_map(|type_id| registry.get(type_id))
+        .filter_map(|registration| registration.data::<ReflectComponent>())
+        .filter_map(|reflect_component| reflect_component.reflect_component(world, entity))
+        .map(|component| component.clone_value())
+        .collect();
+
+    EntitySnapshot {
+        entity,
+        name: entity_ref
+            .get::<Name>()
+            .map(|name| name.as_str().to_owned()),
+        components,
+    }
+}
+
+fn attach_tracked_entities(
+    mut event: Event<'static>,
+    snapshots: &Mutex<Vec<EntitySnapshot>>,
+    registry: &TypeRegistryArc,
+) -> Event<'static> {
+    let snapshots = match snapshots.lock() {
+        Ok(snapshots) => snapshots,
+        Err(_) => return event,
+    };
+    if snapshots.is_empty() {
+        return event;
+    }
+    let registry = registry.read();
+
+    let mut context = Map::new();
+    for snapshot in snapshots.iter() {
+        let mut entity = Map::new();
+        if let Some(name) = &snapshot.name {
+            entity.insert("name".to_owned(), name.clone().into());
+        }
+        for component in &snapshot.components {
+            entity.insert(
+                component.type_name().to_owned(),
+                reflect_to_value(component.as_ref(), &registry),
+            );
+        }
+        context.insert(
+            format!("{:?}", snapshot.entity),
+            Value::Object(entity.into_iter().collect()),
+        );
+    }
+    event
+        .contexts
+        .insert(TRACKED_ENTITIES_CONTEXT.to_owned(), Context::Other(context));
+
+    event
+}
+
+/// Serialize a reflected value for Sentry
+pub(crate) fn reflect_to_value(value: &dyn Reflect, registry: &TypeRegistry) -> Value {
+    to_value(ReflectSerializer::new(value, registry)).unwrap_or(Value::Null)
+}
@@ -0,0 +1,57 @@
+use bevy_sentry::protocol::Value;
+use bevy_sentry::SentryContext;
+use std::collections::HashMap;
+
+#[derive(SentryContext)]
+#[sentry(key = "Character")]
+struct CharacterContext {
+    name: String,
+    #[sentry(rename = "years")]
+    age: u32,
+    #[sentry(redact)]
+    password: String,
+    #[sentry(skip)]
+    _session: u64,
+}
+
+#[derive(SentryContext)]
+struct Inventory {
+    items: Vec<String>,
+    /// Maps with non-string keys can not be serialized
+    slots: HashMap<(u8, u8), String>,
+}
+
+#[test]
+fn derives_context_with_attributes() {
+    let character = CharacterContext {
+        name: "Nikl".to_owned(),
+        age: 38,
+        password: "hunter2".to_owned(),
+        _session: 42,
+    };
+
+    assert_eq!(CharacterContext::key(), "Character");
+    let context = character.context();
+    assert_eq!(
+        context.keys().collect::<Vec<_>>(),
+        vec!["name", "password", "years"]
+    );
+    assert_eq!(context["name"], Value::from("Nikl"));
+    assert_eq!(context["years"], Value::from(38));
+    assert_eq!(context["password"], Value::from("[Filtered]"));
+}
+
+#[test]
+fn leaves_out_fields_that_fail_to_serialize() {
+    let mut slots = HashMap::new();
+    slots.insert((0, 1), "sword".to_owned());
+    let inventory = Inventory {
+        items: vec!["sword".to_owned()],
+        slots,
+    };
+
+    assert_eq!(Inventory::key(), "Inventory");
+    let context = inventory.context();
+    assert_eq!(context.keys().collect::<Vec<_>>(), vec!["items"]);
+    assert_eq!(context["items"], Value::from(vec!["sword"]));
+}